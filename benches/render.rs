@@ -0,0 +1,70 @@
+#![allow(clippy::needless_return)]
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use deployment_tool::config::Config;
+use deployment_tool::{render, App, Changelog, Project};
+use ratatui::{backend::TestBackend, Terminal};
+use serde_json::json;
+
+fn changelog(merge_request_count: usize) -> Changelog {
+    let merge_requests: Vec<serde_json::Value> = (0..merge_request_count)
+        .map(|i| {
+            json!({
+                "ticket_number": format!("T-{}", i),
+                "title": format!("feat(component-{}): merge request number {}", i % 7, i),
+                "github": format!("https://gitlab.example.com/group/project/-/merge_requests/{}", i),
+                "flags": "feature",
+            })
+        })
+        .collect();
+    let changelog = json!({
+        "next_version_number": 252,
+        "current_time": "2025-06-03 10:00",
+        "commit": {
+            "commit_hash": "0123456789abcdef",
+            "title": "Merge branch 'develop'",
+            "author_name": "Jane Doe",
+        },
+        "merge_requests": merge_requests,
+    });
+    return serde_json::from_value(changelog).unwrap();
+}
+
+fn projects(merge_request_count: usize) -> Vec<Project> {
+    return Config::default()
+        .projects
+        .into_iter()
+        .map(|config| Project::new(config, changelog(merge_request_count)))
+        .collect();
+}
+
+fn bench_render(c: &mut Criterion) {
+    let mut terminal = Terminal::new(TestBackend::new(200, 60)).unwrap();
+    let mut app = App::new(projects(300), None, None);
+
+    c.bench_function("render overview with 300 MRs", |b| {
+        b.iter(|| {
+            terminal.draw(|frame| render(frame, &mut app)).unwrap();
+        })
+    });
+
+    app.ready_for_deployment = true;
+    c.bench_function("render deployment view", |b| {
+        b.iter(|| {
+            terminal.draw(|frame| render(frame, &mut app)).unwrap();
+        })
+    });
+}
+
+fn bench_project_new(c: &mut Criterion) {
+    c.bench_function("build project with 300 MRs", |b| {
+        b.iter_batched(
+            || changelog(300),
+            |changelog| Project::new(Config::default().projects.remove(0), changelog),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_render, bench_project_new);
+criterion_main!(benches);
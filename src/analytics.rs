@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use crate::MergeRequest;
+
+const TOP_COMPONENT_COUNT: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum ChangeCategory {
+    Feature,
+    Fix,
+    Chore,
+    Other,
+}
+
+/// What a release is made of: how many features, fixes and chores it ships
+/// and which components are touched the most.
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseComposition {
+    pub features: usize,
+    pub fixes: usize,
+    pub chores: usize,
+    pub other: usize,
+    pub top_components: Vec<(String, usize)>,
+}
+
+impl ReleaseComposition {
+    pub fn from_merge_requests<'a>(merge_requests: impl Iterator<Item = &'a MergeRequest>) -> Self {
+        let mut composition = Self::default();
+        let mut components: HashMap<String, usize> = HashMap::new();
+        for merge_request in merge_requests {
+            match categorize(merge_request) {
+                ChangeCategory::Feature => composition.features += 1,
+                ChangeCategory::Fix => composition.fixes += 1,
+                ChangeCategory::Chore => composition.chores += 1,
+                ChangeCategory::Other => composition.other += 1,
+            }
+            if let Some(component) = component(&merge_request.title) {
+                *components.entry(component).or_default() += 1;
+            }
+        }
+
+        let mut top_components: Vec<(String, usize)> = components.into_iter().collect();
+        top_components.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_components.truncate(TOP_COMPONENT_COUNT);
+        composition.top_components = top_components;
+
+        return composition;
+    }
+
+    pub fn total(&self) -> usize {
+        return self.features + self.fixes + self.chores + self.other;
+    }
+
+    /// One line summary, e.g. "12 MRs: 5 features, 4 fixes, 2 chores, 1 other | Top: cart (3)"
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} MRs: {} features, {} fixes, {} chores, {} other",
+            self.total(),
+            self.features,
+            self.fixes,
+            self.chores,
+            self.other,
+        );
+        if !self.top_components.is_empty() {
+            let components: Vec<String> = self
+                .top_components
+                .iter()
+                .map(|(name, count)| format!("{} ({})", name, count))
+                .collect();
+            summary += &format!(" | Top: {}", components.join(", "));
+        }
+        return summary;
+    }
+}
+
+/// Categorizes a merge request by its conventional commit prefix
+/// ("feat: ...", "fix(cart): ...") and falls back to its flags.
+pub fn categorize(merge_request: &MergeRequest) -> ChangeCategory {
+    let title = merge_request.title.to_lowercase();
+    let prefix: String = title
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    let flags = merge_request.flags.join(",").to_lowercase();
+
+    match prefix.as_str() {
+        "feat" | "feature" => return ChangeCategory::Feature,
+        "fix" | "bugfix" | "hotfix" => return ChangeCategory::Fix,
+        "chore" | "refactor" | "build" | "ci" | "docs" | "test" | "deps" => {
+            return ChangeCategory::Chore
+        }
+        _ => {}
+    }
+    if flags.contains("feature") {
+        return ChangeCategory::Feature;
+    }
+    if flags.contains("bug") || flags.contains("fix") {
+        return ChangeCategory::Fix;
+    }
+    return ChangeCategory::Other;
+}
+
+/// Extracts the scope of a conventional commit title, "fix(cart): ..." -> "cart".
+fn component(title: &str) -> Option<String> {
+    let (head, _) = title.split_once(':')?;
+    let start = head.find('(')?;
+    let end = head.rfind(')')?;
+    if end <= start + 1 {
+        return None;
+    }
+    return Some(head[start + 1..end].trim().to_lowercase());
+}
@@ -0,0 +1,109 @@
+use std::sync::mpsc::{self, Receiver};
+
+use crate::gitlab::{GitlabClient, MergeRequestApprovals};
+use crate::Project;
+
+/// One included merge request of the release with its review evidence.
+#[derive(Debug, Clone)]
+pub struct ApprovalSummary {
+    /// Title of the project
+    pub project: String,
+    pub ticket: String,
+    pub title: String,
+    pub link: String,
+    pub approvals: Result<MergeRequestApprovals, String>,
+}
+
+impl ApprovalSummary {
+    /// Not approved, code owners missing or discussions left open.
+    pub fn needs_attention(&self) -> bool {
+        let Ok(approvals) = &self.approvals else {
+            return true;
+        };
+        return approvals.approved_by.is_empty()
+            || approvals.code_owners_approved == Some(false)
+            || approvals.unresolved_discussions > 0;
+    }
+}
+
+/// The included merge requests of the projects, their approvals are not loaded yet.
+pub fn included(projects: &[Project]) -> Vec<ApprovalSummary> {
+    return projects
+        .iter()
+        .flat_map(|project| {
+            return project
+                .included_merge_requests()
+                .map(|merge_request| ApprovalSummary {
+                    project: project.config.title.clone(),
+                    ticket: merge_request.ticket_number.clone(),
+                    title: merge_request.title.clone(),
+                    link: merge_request.github.clone(),
+                    approvals: Err("Not loaded".to_string()),
+                });
+        })
+        .collect();
+}
+
+/// Loads the approvals of the merge requests one after another, their `approvals` are ignored.
+pub fn load(gitlab: &GitlabClient, merge_requests: Vec<ApprovalSummary>) -> Vec<ApprovalSummary> {
+    return merge_requests
+        .into_iter()
+        .map(|summary| {
+            let approvals = gitlab
+                .approvals(&summary.link)
+                .map_err(|error| format!("{:#}", error));
+            return ApprovalSummary {
+                approvals,
+                ..summary
+            };
+        })
+        .collect();
+}
+
+/// Approvals of every merge request in the release, loaded in the background.
+#[derive(Default)]
+pub struct Approvals {
+    /// None until the first load finished
+    pub summaries: Option<Vec<ApprovalSummary>>,
+    /// Index into `summaries`
+    pub selected: usize,
+    receiver: Option<Receiver<Vec<ApprovalSummary>>>,
+}
+
+impl Approvals {
+    /// Loads the approvals of the given merge requests again.
+    pub fn refresh(&mut self, gitlab: &GitlabClient, merge_requests: Vec<ApprovalSummary>) {
+        if self.is_loading() {
+            return;
+        }
+        let gitlab = gitlab.clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(load(&gitlab, merge_requests));
+        });
+        self.receiver = Some(receiver);
+    }
+
+    /// Picks up the approvals once they are loaded, call this before rendering.
+    pub fn update(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        if let Ok(summaries) = receiver.try_recv() {
+            self.summaries = Some(summaries);
+            self.selected = 0;
+            self.receiver = None;
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        return self.receiver.is_some();
+    }
+
+    pub fn count(&self) -> usize {
+        return self
+            .summaries
+            .as_ref()
+            .map_or(0, |summaries| summaries.len());
+    }
+}
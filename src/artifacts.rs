@@ -0,0 +1,85 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    File,
+    Url,
+    /// A value captured from a command's output
+    Variable,
+}
+
+/// Something a deployment step produced, later steps can read it by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub name: String,
+    pub kind: ArtifactKind,
+    pub value: String,
+}
+
+impl Artifact {
+    pub fn new(name: &str, kind: ArtifactKind, value: &str) -> Self {
+        return Self {
+            name: name.to_string(),
+            kind,
+            value: value.to_string(),
+        };
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} = {}", self.name, self.value);
+    }
+}
+
+/// Everything the steps of a deployment produced so far, a step that runs again replaces its
+/// earlier values.
+#[derive(Debug, Clone, Default)]
+pub struct Artifacts {
+    artifacts: Vec<Artifact>,
+}
+
+impl Artifacts {
+    pub fn insert(&mut self, artifact: Artifact) {
+        match self
+            .artifacts
+            .iter_mut()
+            .find(|existing| existing.name == artifact.name)
+        {
+            Some(existing) => *existing = artifact,
+            None => self.artifacts.push(artifact),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        return self
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.name == name)
+            .map(|artifact| artifact.value.as_str());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Artifact> {
+        return self.artifacts.iter();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.artifacts.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.artifacts.clear();
+    }
+
+    /// Replaces `{name}` with the value of every artifact.
+    pub fn replace_placeholders(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for artifact in self.artifacts.iter() {
+            text = text.replace(&format!("{{{}}}", artifact.name), &artifact.value);
+        }
+        return text;
+    }
+}
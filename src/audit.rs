@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::history::DeploymentRecord;
+
+/// Writes everything auditors ask about a deployment into a zip file: the approvals, the
+/// sign-offs and step outcomes, the release notes and the raw history entry.
+pub fn export(record: &DeploymentRecord, path: &Path) -> Result<()> {
+    let file =
+        File::create(path).wrap_err_with(|| format!("Could not create {}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let files = [
+        ("approvals.md", approvals(record)),
+        ("deployment.md", report(record)),
+        ("release-notes.md", record.release_notes.clone()),
+        ("history-entry.json", serde_json::to_string_pretty(record)?),
+    ];
+    for (name, content) in files.iter() {
+        zip.start_file(*name, SimpleFileOptions::default())?;
+        zip.write_all(content.as_bytes())?;
+    }
+    zip.finish()
+        .wrap_err_with(|| format!("Could not write {}", path.display()))?;
+    return Ok(());
+}
+
+/// Who approved the merge requests, as a markdown table.
+fn approvals(record: &DeploymentRecord) -> String {
+    let mut text = format!("# Approvals of {}\n\n", record.versions_summary());
+    if record.approvals.is_empty() {
+        text.push_str("No approvals were recorded for this deployment.\n");
+        return text;
+    }
+    text.push_str("| Project | Ticket | Merge request | Approved by | Code owners | Unresolved discussions |\n");
+    text.push_str("| --- | --- | --- | --- | --- | --- |\n");
+    for approval in record.approvals.iter() {
+        let (approved_by, code_owners, unresolved) = match &approval.error {
+            Some(error) => (format!("not loaded: {}", error), "", String::new()),
+            None => (
+                approval.approved_by.join(", "),
+                match approval.code_owners_approved {
+                    Some(true) => "approved",
+                    Some(false) => "missing",
+                    None => "no rules",
+                },
+                approval.unresolved_discussions.to_string(),
+            ),
+        };
+        text.push_str(&format!(
+            "| {} | {} | [{}]({}) | {} | {} | {} |\n",
+            approval.project,
+            approval.ticket,
+            approval.title.replace('|', "\\|"),
+            approval.link,
+            approved_by,
+            code_owners,
+            unresolved
+        ));
+    }
+    return text;
+}
+
+/// Who deployed when, the options they signed off on and how every step ended.
+fn report(record: &DeploymentRecord) -> String {
+    let mut text = format!("# Deployment of {}\n\n", record.versions_summary());
+    text.push_str(&format!("- Finished: {}\n", record.finished_at));
+    text.push_str(&format!(
+        "- Deployed by: {} ({})\n",
+        record.user,
+        if record.verified {
+            "logged in with OIDC"
+        } else {
+            "$USER, not verified"
+        }
+    ));
+    text.push_str(&format!(
+        "- Result: {}\n",
+        if record.has_failed() {
+            "failed"
+        } else {
+            "succeeded"
+        }
+    ));
+
+    let tags: Vec<String> = record
+        .versions
+        .iter()
+        .filter_map(|version| Some(format!("{} ({})", version.tag.as_ref()?, version.project)))
+        .collect();
+    if !tags.is_empty() {
+        text.push_str(&format!("- Tags: {}\n", tags.join(", ")));
+    }
+
+    text.push_str("\n## Checked options\n\n");
+    if record.options.is_empty() {
+        text.push_str("None\n");
+    }
+    for option in record.options.iter() {
+        text.push_str(&format!("- {}\n", option));
+    }
+
+    text.push_str("\n## Steps\n\n");
+    for step in record.steps.iter() {
+        text.push_str(&format!("- {}: {}\n", step.label, step.outcome));
+    }
+
+    if !record.artifacts.is_empty() {
+        text.push_str("\n## Produced\n\n");
+        for artifact in record.artifacts.iter() {
+            text.push_str(&format!("- {}\n", artifact));
+        }
+    }
+    return text;
+}
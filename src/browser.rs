@@ -0,0 +1,22 @@
+use std::process::{Command, Stdio};
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+
+#[cfg(target_os = "macos")]
+const OPEN_COMMAND: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const OPEN_COMMAND: &str = "xdg-open";
+
+/// Opens the URL in the system browser without waiting for it.
+pub fn open(url: &str) -> Result<()> {
+    // Anything the opener prints would end up in the middle of the UI
+    Command::new(OPEN_COMMAND)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .wrap_err_with(|| format!("Could not run {}", OPEN_COMMAND))?;
+    return Ok(());
+}
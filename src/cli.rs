@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+/// Review pending changelogs and deploy them.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Only show and deploy these projects (by name from the config), can be repeated
+    #[arg(long = "project", value_name = "NAME")]
+    pub projects: Vec<String>,
+
+    /// Config file to use instead of ~/.config/deployment-tool/config.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Environment variable holding the GitLab token
+    #[arg(long, value_name = "NAME", default_value = "GITLAB_TOKEN")]
+    pub token_env: String,
+
+    /// Show what each deployment step would do without running it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Run the deployment without the interactive UI and print its progress
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Send the release mail as part of the deployment
+    #[arg(long)]
+    pub release_mail: bool,
+
+    /// Keep refreshing the pending merge requests and alert as configured in [watch]
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Print the changelogs instead of starting the interactive UI
+    #[arg(long)]
+    pub no_tui: bool,
+
+    /// Learn the ropes with a guided dry run release of made up projects
+    #[arg(long)]
+    pub tutorial: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Work with the deployment steps
+    Steps {
+        #[command(subcommand)]
+        command: StepsCommand,
+    },
+    /// Evidence for compliance audits
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StepsCommand {
+    /// Check the deployment steps of the config without running anything
+    Lint,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    /// Bundle the approvals, sign-offs, step outcomes and release notes of a release into a zip
+    Export {
+        /// Released version, e.g. v252
+        #[arg(long, value_name = "VERSION")]
+        release: String,
+
+        /// Zip file to write, defaults to audit-<release>.zip
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
@@ -0,0 +1,1076 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use color_eyre::eyre::{bail, WrapErr};
+use color_eyre::Result;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::gitlab::GitlabClient;
+use crate::MergeRequest;
+
+/// How long the webhook of a column may take per merge request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_CHANGELOG_GENERATOR: &str =
+    "/home/mamazu/packages/brille24/ecom-docker/www/sulu/etc/change_log_generator.php";
+
+/// Contents of `~/.config/deployment-tool/config.toml`.
+///
+/// ```toml
+/// # Fetch changelogs and trigger pipelines through the GitLab API
+/// # Opened with O on a merge request, {ticket} is replaced
+/// ticket_url = "https://jira.example.com/browse/{ticket}"
+///
+/// [gitlab]
+/// url = "https://gitlab.example.com"
+///
+/// [changelog]
+/// command = "php"
+/// args = ["etc/change_log_generator.php", "--format=json", "--projectId={project_id}", "--token={token}"]
+///
+/// [[projects]]
+/// name = "sulu"
+/// project_id = 251
+/// title = "Sulu"
+/// deploy_command = ["./deploy.sh", "{project_id}", "{version}"]
+/// extract = [{ name = "migrations", regex = "Ran (\\d+) migrations" }]
+///
+/// [release_notes]
+/// path = "release-notes.md"
+///
+/// [mail]
+/// host = "smtp.example.com"
+/// from = "deployments@example.com"
+/// recipients = ["team@example.com"]
+///
+/// [notifications]
+/// webhooks = ["https://hooks.slack.com/services/..."]
+///
+/// [watch]
+/// threshold = 20
+///
+/// [rotation]
+/// start = "2026-01-05"
+/// people = [{ name = "Anna", user = "anna", email = "anna@example.com" }, { name = "Ben" }]
+///
+/// [time_box]
+/// max_minutes = 30
+///
+/// # Step definitions shared between projects, see `StepDefinition`
+/// include = ["steps.toml", "gitlab.example.com/ops/deploy-steps//php-app.toml@v3"]
+///
+/// # Replaces the default steps, see `StepConfig`
+/// [[deployment_steps]]
+/// type = "release_notes"
+///
+/// [[deployment_steps]]
+/// type = "pipeline"
+/// project = "sulu"
+/// option = "Sulu Deployment"
+///
+/// # Extra columns in the merge request table, see `ColumnConfig`
+/// [[columns]]
+/// title = "QA"
+/// label = "qa::"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// Link to a ticket in the issue tracker, `{ticket}` is replaced with e.g. SHOP-123
+    pub ticket_url: Option<String>,
+    pub gitlab: Option<GitlabConfig>,
+    /// Log in before deploying, so the history names who really deployed
+    pub oidc: Option<OidcConfig>,
+    /// Where finished deployments are kept
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Anonymous usage reports, nothing is sent without it
+    pub telemetry: Option<TelemetryConfig>,
+    #[serde(default)]
+    pub changelog: ChangelogCommand,
+    #[serde(default)]
+    pub release_notes: ReleaseNotesConfig,
+    /// SMTP server the release mail is sent through
+    pub mail: Option<MailConfig>,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// Who is release manager when, shown in the UI and named in announcements
+    pub rotation: Option<RotationConfig>,
+    /// How long a deployment may take before it is held
+    pub time_box: Option<TimeBoxConfig>,
+    /// Files with more step definitions, paths relative to the config file or
+    /// `<gitlab host>/<project path>//<file>@<ref>`
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Deployment steps projects can use by name
+    #[serde(default)]
+    pub steps: BTreeMap<String, StepDefinition>,
+    /// Extra columns in the merge request table
+    #[serde(default)]
+    pub columns: Vec<ColumnConfig>,
+    /// The deployment steps in the order they run, see `Config::step_list` for the default
+    #[serde(default)]
+    pub deployment_steps: Vec<StepConfig>,
+    /// Directory of the config file, local includes are relative to it
+    #[serde(skip)]
+    directory: PathBuf,
+    #[serde(default = "default_projects")]
+    pub projects: Vec<ProjectConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitlabConfig {
+    pub url: String,
+    /// Fetch changelogs from the API instead of running the changelog command
+    #[serde(default = "default_true")]
+    pub changelog: bool,
+    /// Cancel the pipeline a cancelled (or timed out) deployment was waiting for
+    #[serde(default)]
+    pub cancel_pipelines: bool,
+    /// Give up waiting for a pipeline after this many seconds
+    pub pipeline_timeout_seconds: Option<u64>,
+}
+
+/// OpenID Connect provider to log in with, it has to support the device flow.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    /// Issuer URL, e.g. https://login.example.com/realms/ops
+    pub issuer: String,
+    /// A public client, there is no secret to keep on every machine
+    pub client_id: String,
+    #[serde(default = "default_oidc_scope")]
+    pub scope: String,
+}
+
+fn default_oidc_scope() -> String {
+    return "openid profile email".to_string();
+}
+
+/// Storage of the deployment history. A shared database or bucket lets several installations
+/// write to one history.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum HistoryConfig {
+    /// A JSON file, `$XDG_DATA_HOME/deployment-tool/history.json` without a path
+    File { path: Option<PathBuf> },
+    /// A table in an SQLite database, it is created when it does not exist
+    Sqlite { path: PathBuf },
+    /// A table in a PostgreSQL database, the connection string is read from `url_env`
+    Postgres {
+        #[serde(default = "default_history_url_env")]
+        url_env: String,
+    },
+    /// One JSON object per deployment in an S3 bucket, the credentials are read from
+    /// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`
+    S3 {
+        /// e.g. https://s3.eu-central-1.amazonaws.com
+        endpoint: String,
+        region: String,
+        bucket: String,
+        /// Put in front of every object name, e.g. `deployments/`
+        #[serde(default)]
+        prefix: String,
+    },
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        return Self::File { path: None };
+    }
+}
+
+fn default_history_url_env() -> String {
+    return "HISTORY_DATABASE_URL".to_string();
+}
+
+/// Where the tool reports which features were used and what kinds of errors happened, see
+/// `telemetry::Usage` for everything that is sent.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelemetryConfig {
+    pub endpoint: String,
+}
+
+fn default_true() -> bool {
+    return true;
+}
+
+/// Command printing the changelog of a project as JSON. `{project_id}` and
+/// `{token}` in the arguments are replaced before running it.
+#[derive(Debug, Deserialize)]
+pub struct ChangelogCommand {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+/// Where and how the release notes step writes the Markdown release notes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReleaseNotesConfig {
+    /// File to write to, `-` prints the release notes to stdout
+    pub path: String,
+    /// Rendered for every project, placeholders: `{title}`, `{version}`, `{summary}`,
+    /// `{merge_requests}`
+    pub template: String,
+    /// Rendered for every merge request, placeholders: `{ticket}`, `{title}`, `{link}`, `{flags}`
+    pub merge_request_template: String,
+    /// Where the written file is published (e.g. a shared drive), linked in the release mail
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MailConfig {
+    pub host: String,
+    /// Connects with STARTTLS, so the submission port by default
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    /// Logs in with this user and the `SMTP_PASSWORD` environment variable if set
+    pub username: Option<String>,
+    pub from: String,
+    pub recipients: Vec<String>,
+    /// `{versions}` and `{date}` are replaced
+    #[serde(default = "default_mail_subject")]
+    pub subject: String,
+}
+
+fn default_smtp_port() -> u16 {
+    return 587;
+}
+
+fn default_mail_subject() -> String {
+    return "Release {date}: {versions}".to_string();
+}
+
+/// Slack or Teams incoming webhooks told about deployment progress.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+}
+
+/// When `--watch` refreshes the changelogs and what it alerts about.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Seconds between two refreshes
+    pub interval: u64,
+    /// Alert when a project has this many pending merge requests
+    pub threshold: Option<usize>,
+    /// Alert when a merge request with one of these flags is merged
+    pub flags: Vec<String>,
+    /// Also show alerts as desktop notifications (notify-send)
+    pub desktop: bool,
+    /// Refresh the interactive overview every `interval` seconds too
+    pub auto_refresh: bool,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        return Self {
+            interval: 300,
+            threshold: None,
+            flags: vec!["hotfix".to_string()],
+            desktop: true,
+            auto_refresh: false,
+        };
+    }
+}
+
+/// A deployment running longer than `max_minutes` is held before its next irreversible step
+/// (release mail, project deployments) until someone decides how to go on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeBoxConfig {
+    pub max_minutes: u64,
+    /// How much longer the deployment may run when the window is extended
+    #[serde(default = "default_extension_minutes")]
+    pub extension_minutes: u64,
+}
+
+fn default_extension_minutes() -> u64 {
+    return 15;
+}
+
+/// Release managers taking turns, the first person starts on `start`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RotationConfig {
+    pub people: Vec<Person>,
+    /// First day of the first person's turn, e.g. "2026-01-05"
+    pub start: String,
+    /// Length of a turn in days
+    #[serde(default = "default_cadence_days")]
+    pub cadence_days: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Person {
+    pub name: String,
+    /// Login name (`$USER`, or the OIDC username), to warn when someone else starts a deployment
+    pub user: Option<String>,
+    /// Added to the release mail
+    pub email: Option<String>,
+}
+
+fn default_cadence_days() -> u32 {
+    return 7;
+}
+
+impl RotationConfig {
+    /// The person whose turn it is on the given day.
+    pub fn release_manager(&self, today: chrono::NaiveDate) -> Result<&Person> {
+        if self.people.is_empty() || self.cadence_days == 0 {
+            bail!("The rotation needs people and a cadence of at least one day");
+        }
+        let start = chrono::NaiveDate::parse_from_str(&self.start, "%Y-%m-%d")
+            .wrap_err_with(|| format!("Invalid rotation start {}", self.start))?;
+        let turns = (today - start)
+            .num_days()
+            .div_euclid(self.cadence_days as i64);
+        return Ok(&self.people[turns.rem_euclid(self.people.len() as i64) as usize]);
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectConfig {
+    pub name: String,
+    pub project_id: u64,
+    pub title: String,
+    /// Command run by the project's deployment step, `{project_id}` and `{version}` are replaced
+    #[serde(default)]
+    pub deploy_command: Vec<String>,
+    /// Branch or tag to run the deployment pipeline on, defaults to the default branch
+    pub pipeline_ref: Option<String>,
+    /// Ask before checking the project's deployment option, e.g. for production
+    #[serde(default)]
+    pub confirm: bool,
+    /// Values captured from the deploy command's output, for later steps and notifications
+    #[serde(default)]
+    pub extract: Vec<Extractor>,
+    /// Name of a step definition providing the deploy command and extractors
+    pub uses: Option<String>,
+    /// Width and visibility of the extra columns for this project, by title
+    #[serde(default)]
+    pub columns: BTreeMap<String, ColumnOverride>,
+}
+
+/// A deploy command with its extractors that several projects share, defined under
+/// `[steps.<name>]` in the config or an included file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StepDefinition {
+    pub deploy_command: Vec<String>,
+    #[serde(default)]
+    pub extract: Vec<Extractor>,
+}
+
+/// Contents of an included file.
+#[derive(Debug, Deserialize)]
+struct StepLibrary {
+    #[serde(default)]
+    steps: BTreeMap<String, StepDefinition>,
+}
+
+/// Captures a value from a line of output, either the first group of `regex` (or the whole
+/// match without groups) or the value at the JSON pointer `json` (e.g. "/artifact/url") of a
+/// line that is a JSON document. The last matching line wins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Extractor {
+    pub name: String,
+    /// Compiled when the config is loaded, so an invalid pattern is a config error
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub regex: Option<Regex>,
+    pub json: Option<String>,
+}
+
+fn deserialize_regex<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Regex>, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    return Regex::new(&pattern)
+        .map(Some)
+        .map_err(serde::de::Error::custom);
+}
+
+impl Extractor {
+    /// The captured value of one line of output.
+    pub fn extract(&self, line: &str) -> Option<String> {
+        if let Some(regex) = &self.regex {
+            let captures = regex.captures(line)?;
+            let capture = captures.get(1).or_else(|| captures.get(0))?;
+            return Some(capture.as_str().to_string());
+        }
+        let pointer = self.json.as_ref()?;
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        match value.pointer(pointer)? {
+            serde_json::Value::String(text) => return Some(text.clone()),
+            value => return Some(value.to_string()),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.regex.is_some() == self.json.is_some() {
+            bail!("{} needs either a regex or a json pointer", self.name);
+        }
+        return Ok(());
+    }
+}
+
+/// One step of the deployment. Steps with an `option` only run when the checkbox with that
+/// label is checked in the deployment view, steps naming the same option share it. A step
+/// starts when the one before it is done, or with `depends_on` when the named ones are: steps
+/// whose dependencies are done run at the same time.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StepConfig {
+    #[serde(flatten)]
+    pub kind: StepType,
+    /// Shown in the step list, derived from the type by default
+    pub label: Option<String>,
+    /// How `depends_on` of later steps refers to this one
+    pub name: Option<String>,
+    /// Names of earlier steps that have to be done first, `[]` to start right away
+    pub depends_on: Option<Vec<String>>,
+    pub option: Option<String>,
+    /// Whether the option starts checked
+    #[serde(default = "default_true")]
+    pub checked: bool,
+    /// Ask before checking the option
+    #[serde(default)]
+    pub confirm: bool,
+    /// Kill the step's command or give up its pipeline after this many seconds
+    pub timeout_seconds: Option<u64>,
+    /// How often a failed step is tried again by itself before it pauses
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay_seconds: u64,
+}
+
+fn default_retry_delay() -> u64 {
+    return 10;
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepType {
+    /// Writes the release notes as configured in `[release_notes]`
+    ReleaseNotes,
+    /// Sends the release mail as configured in `[mail]`
+    Mail,
+    /// Runs the project's deploy command, or its GitLab pipeline without one
+    Pipeline { project: String },
+    /// Runs a command, `{name}` is replaced with what earlier steps produced. With a `project`
+    /// it only runs when that project is deployed, and `{project_id}` and `{version}` work too.
+    Shell {
+        command: Vec<String>,
+        #[serde(default)]
+        project: Option<String>,
+    },
+    /// Posts the message to a Slack or Teams incoming webhook, `{name}` is replaced as well
+    Webhook { url: String, message: String },
+    /// Creates the annotated tag of the project's release and pushes it. With a `repository`
+    /// checkout git tags its HEAD, otherwise GitLab tags `pipeline_ref` or the default branch.
+    /// `{version}` and `{project_id}` are replaced in the tag and message.
+    Tag {
+        project: String,
+        /// Name of the tag
+        #[serde(default = "default_tag_name")]
+        tag: String,
+        #[serde(default = "default_tag_message")]
+        message: String,
+        repository: Option<PathBuf>,
+        /// Where git pushes the tag to
+        #[serde(default = "default_remote")]
+        remote: String,
+    },
+    /// Uploads a built static site to a bucket and invalidates the CDN in front of it
+    StaticSite(StaticSiteConfig),
+}
+
+/// Where a static site is uploaded to, the credentials are read from `AWS_ACCESS_KEY_ID` and
+/// `AWS_SECRET_ACCESS_KEY`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticSiteConfig {
+    /// Directory of the built site
+    pub source: PathBuf,
+    /// e.g. https://s3.eu-central-1.amazonaws.com, or https://storage.googleapis.com for GCS
+    /// with HMAC keys
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Put in front of every object name, e.g. `www/`
+    #[serde(default)]
+    pub prefix: String,
+    /// The first rule whose pattern matches a file sets its `Cache-Control`
+    #[serde(default)]
+    pub cache_control: Vec<CacheControlRule>,
+    /// Remove the objects under the prefix that are not part of the site anymore
+    #[serde(default)]
+    pub delete: bool,
+    /// CloudFront distribution whose cache is invalidated once everything is uploaded
+    pub distribution_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheControlRule {
+    /// Path relative to `source`, `*` matches within a directory and `**` across them,
+    /// e.g. `assets/**`
+    #[serde(deserialize_with = "deserialize_glob")]
+    pub pattern: Regex,
+    /// e.g. `public, max-age=31536000, immutable`
+    pub value: String,
+}
+
+fn deserialize_glob<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    return glob_regex(&pattern).map_err(serde::de::Error::custom);
+}
+
+/// The regex matching the same paths as the glob pattern.
+fn glob_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut regex = "^".to_string();
+    let mut rest = pattern;
+    while let Some(char) = rest.chars().next() {
+        let (part, length) = if rest.starts_with("**/") {
+            ("(.*/)?".to_string(), 3)
+        } else if rest.starts_with("**") {
+            (".*".to_string(), 2)
+        } else if char == '*' {
+            ("[^/]*".to_string(), 1)
+        } else if char == '?' {
+            ("[^/]".to_string(), 1)
+        } else {
+            (regex::escape(&char.to_string()), char.len_utf8())
+        };
+        regex += &part;
+        rest = &rest[length..];
+    }
+    return Regex::new(&(regex + "$"));
+}
+
+fn default_tag_name() -> String {
+    return "v{version}".to_string();
+}
+
+fn default_tag_message() -> String {
+    return "Release v{version}".to_string();
+}
+
+fn default_remote() -> String {
+    return "origin".to_string();
+}
+
+impl StepType {
+    /// The project the step belongs to, it is left out when the project is not deployed.
+    pub fn project(&self) -> Option<&String> {
+        match self {
+            StepType::Pipeline { project } | StepType::Tag { project, .. } => return Some(project),
+            StepType::Shell { project, .. } => return project.as_ref(),
+            _ => return None,
+        }
+    }
+}
+
+impl StepConfig {
+    fn new(kind: StepType, option: Option<String>, checked: bool, confirm: bool) -> Self {
+        return Self {
+            kind,
+            label: None,
+            name: None,
+            depends_on: None,
+            option,
+            checked,
+            confirm,
+            timeout_seconds: None,
+            retries: 0,
+            retry_delay_seconds: default_retry_delay(),
+        };
+    }
+}
+
+/// The steps when none are configured: the release notes, the release mail and every
+/// project's deployment, each with its own option.
+pub fn default_steps(projects: &[ProjectConfig]) -> Vec<StepConfig> {
+    let mut steps = vec![
+        StepConfig::new(StepType::ReleaseNotes, None, true, false),
+        StepConfig::new(
+            StepType::Mail,
+            Some("Send Release Mail".to_string()),
+            false,
+            true,
+        ),
+    ];
+    for project in projects.iter() {
+        steps.push(StepConfig::new(
+            StepType::Pipeline {
+                project: project.name.clone(),
+            },
+            Some(format!("{} Deployment", project.title)),
+            true,
+            project.confirm,
+        ));
+    }
+    return steps;
+}
+
+/// An extra column in the merge request table, its value comes from one of: the first label
+/// starting with `label` (without that prefix), a `<field>: value` line in the merge request's
+/// description, or the first line of what `webhook` responds (`{ticket}`, `{link}` and
+/// `{project_id}` are replaced).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnConfig {
+    pub title: String,
+    pub label: Option<String>,
+    pub field: Option<String>,
+    pub webhook: Option<String>,
+    #[serde(default = "default_column_width")]
+    pub width: u16,
+}
+
+fn default_column_width() -> u16 {
+    return 12;
+}
+
+/// How a project shows one of the extra columns.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnOverride {
+    pub width: Option<u16>,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+}
+
+impl ColumnConfig {
+    /// The value of the column for a merge request, webhooks are called right away.
+    pub fn value(&self, merge_request: &MergeRequest, project: &ProjectConfig) -> Option<String> {
+        if let Some(prefix) = &self.label {
+            let label = merge_request
+                .flags
+                .iter()
+                .find(|label| label.starts_with(prefix.as_str()))?;
+            let value = label[prefix.len()..].trim();
+            if value.is_empty() {
+                return Some(label.clone());
+            }
+            return Some(value.to_string());
+        }
+        if let Some(field) = &self.field {
+            return merge_request.description.lines().find_map(|line| {
+                // Also matches list items and bold names, e.g. "- **QA:** done"
+                let line = line.trim_start_matches(['-', '*', ' ']);
+                let (name, value) = line.split_once(':')?;
+                if !name.trim_end_matches('*').eq_ignore_ascii_case(field) {
+                    return None;
+                }
+                return Some(value.trim_matches(['*', ' ']).to_string());
+            });
+        }
+        let url = self
+            .webhook
+            .as_ref()?
+            .replace("{ticket}", &merge_request.ticket_number)
+            .replace("{link}", &merge_request.github)
+            .replace("{project_id}", &project.project_id.to_string());
+        let body = ureq::AgentBuilder::new()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .get(&url)
+            .call()
+            .ok()?
+            .into_string()
+            .ok()?;
+        return body.lines().next().map(|line| line.trim().to_string());
+    }
+
+    fn validate(&self) -> Result<()> {
+        let sources = [&self.label, &self.field, &self.webhook]
+            .iter()
+            .filter(|source| source.is_some())
+            .count();
+        if sources != 1 {
+            bail!(
+                "Column {} needs exactly one of label, field or webhook",
+                self.title
+            );
+        }
+        return Ok(());
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        return Self {
+            ticket_url: None,
+            gitlab: None,
+            oidc: None,
+            history: HistoryConfig::default(),
+            telemetry: None,
+            changelog: ChangelogCommand::default(),
+            release_notes: ReleaseNotesConfig::default(),
+            mail: None,
+            notifications: NotificationsConfig::default(),
+            watch: WatchConfig::default(),
+            rotation: None,
+            time_box: None,
+            include: vec![],
+            steps: BTreeMap::new(),
+            columns: vec![],
+            deployment_steps: vec![],
+            directory: PathBuf::new(),
+            projects: default_projects(),
+        };
+    }
+}
+
+impl Default for ReleaseNotesConfig {
+    fn default() -> Self {
+        return Self {
+            path: "release-notes.md".to_string(),
+            template: "## {title} v{version}\n\n{summary}\n\n{merge_requests}\n".to_string(),
+            merge_request_template: "- [{ticket}]({link}) {title} {flags}".to_string(),
+            url: None,
+        };
+    }
+}
+
+impl Default for ChangelogCommand {
+    fn default() -> Self {
+        // Overridable so the tool can run where that checkout does not exist (e.g. in a container)
+        let generator = std::env::var("CHANGELOG_GENERATOR")
+            .unwrap_or_else(|_| DEFAULT_CHANGELOG_GENERATOR.to_string());
+        return Self {
+            command: "php".to_string(),
+            args: vec![
+                generator,
+                "--format=json".to_string(),
+                "--projectId={project_id}".to_string(),
+                "--token={token}".to_string(),
+            ],
+        };
+    }
+}
+
+impl ChangelogCommand {
+    pub fn args_for(&self, project: &ProjectConfig, token: &str) -> Vec<String> {
+        return self
+            .args
+            .iter()
+            .map(|arg| {
+                return arg
+                    .replace("{project_id}", &project.project_id.to_string())
+                    .replace("{token}", token);
+            })
+            .collect();
+    }
+}
+
+fn default_projects() -> Vec<ProjectConfig> {
+    return vec![
+        ProjectConfig {
+            name: "sulu".to_string(),
+            project_id: 251,
+            title: "Sulu".to_string(),
+            deploy_command: vec![],
+            pipeline_ref: None,
+            confirm: false,
+            extract: vec![],
+            uses: None,
+            columns: BTreeMap::new(),
+        },
+        ProjectConfig {
+            name: "sylius".to_string(),
+            project_id: 65,
+            title: "Sylius".to_string(),
+            deploy_command: vec![],
+            pipeline_ref: None,
+            confirm: false,
+            extract: vec![],
+            uses: None,
+            columns: BTreeMap::new(),
+        },
+    ];
+}
+
+/// Reads an included file, either from disk or as `<host>/<project path>//<file>@<ref>` from
+/// a GitLab repository (over https unless the host starts with `http://`). Remote includes
+/// have to be pinned to a ref, only the configured GitLab instance gets the token.
+fn load_step_library(
+    include: &str,
+    directory: &Path,
+    gitlab: Option<&GitlabConfig>,
+    token: &str,
+) -> Result<StepLibrary> {
+    let (scheme, location) = include.split_once("://").unwrap_or(("https", include));
+    let content = match location.split_once("//") {
+        Some((repository, file)) => {
+            let Some((file, git_ref)) = file.rsplit_once('@') else {
+                bail!("Remote includes need a ref, e.g. {}@v1", include);
+            };
+            let Some((host, project)) = repository.split_once('/') else {
+                bail!("Expected <host>/<project path>//<file>@<ref>");
+            };
+            let base_url = format!("{}://{}", scheme, host);
+            let trusted = gitlab.is_some_and(|gitlab| same_origin(&gitlab.url, &base_url));
+            let token = if trusted { token } else { "" };
+            GitlabClient::new(&base_url, token).repository_file(project, file, git_ref)?
+        }
+        None => {
+            let path = directory.join(include);
+            std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("Could not read {}", path.display()))?
+        }
+    };
+    return toml::from_str(&content).wrap_err("Invalid step definitions");
+}
+
+/// Whether both URLs have the same scheme, host and port.
+fn same_origin(url: &str, other: &str) -> bool {
+    let origin = |url: &str| {
+        let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+        let host = rest.split('/').next().unwrap_or_default();
+        return (scheme.to_lowercase(), host.to_lowercase());
+    };
+    return origin(url) == origin(other);
+}
+
+/// `$XDG_CONFIG_HOME/deployment-tool/config.toml`, falling back to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    return Some(config_home.join("deployment-tool").join("config.toml"));
+}
+
+impl Config {
+    /// Loads the config from the default location, or the built-in defaults if there is none.
+    pub fn load_default() -> Result<Self> {
+        match default_path() {
+            Some(path) if path.exists() => return Self::load(&path),
+            _ => return Ok(Self::default()),
+        }
+    }
+
+    /// The configured deployment steps, or the default ones.
+    pub fn step_list(&self) -> Vec<StepConfig> {
+        if self.deployment_steps.is_empty() {
+            return default_steps(&self.projects);
+        }
+        return self.deployment_steps.clone();
+    }
+
+    /// Restricts the config to the projects with the given names, all are kept when none are given.
+    pub fn select_projects(&mut self, names: &[String]) -> Result<()> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        for name in names.iter() {
+            if !self.projects.iter().any(|project| &project.name == name) {
+                let available: Vec<&str> = self
+                    .projects
+                    .iter()
+                    .map(|project| project.name.as_str())
+                    .collect();
+                bail!(
+                    "Unknown project {}, available are: {}",
+                    name,
+                    available.join(", ")
+                );
+            }
+        }
+        // The steps of the other projects stay, `Deployment::with_steps` leaves them out and
+        // passes on what they depend on
+        self.projects
+            .retain(|project| names.contains(&project.name));
+        return Ok(());
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Could not read config file {}", path.display()))?;
+        let mut config: Self = toml::from_str(&content)
+            .wrap_err_with(|| format!("Invalid config file {}", path.display()))?;
+        config.directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        if config.projects.is_empty() {
+            bail!("No projects are configured in {}", path.display());
+        }
+        if let Some(rotation) = &config.rotation {
+            rotation
+                .release_manager(chrono::Local::now().date_naive())
+                .wrap_err_with(|| format!("Invalid rotation in {}", path.display()))?;
+        }
+        // Only earlier steps can be depended on, so there are no cycles
+        let mut names: Vec<&String> = vec![];
+        for step in config.deployment_steps.iter() {
+            for dependency in step.depends_on.iter().flatten() {
+                if !names.contains(&dependency) {
+                    bail!(
+                        "Deployment step depends on {}, which is no earlier step, in {}",
+                        dependency,
+                        path.display()
+                    );
+                }
+            }
+            if let Some(name) = &step.name {
+                if names.contains(&name) {
+                    bail!(
+                        "Two deployment steps are named {} in {}",
+                        name,
+                        path.display()
+                    );
+                }
+                names.push(name);
+            }
+            if let Some(project) = step.kind.project() {
+                if !config.projects.iter().any(|config| &config.name == project) {
+                    bail!(
+                        "Deployment step for unknown project {} in {}",
+                        project,
+                        path.display()
+                    );
+                }
+            }
+        }
+        for column in config.columns.iter() {
+            column
+                .validate()
+                .wrap_err_with(|| format!("Invalid config file {}", path.display()))?;
+        }
+        for project in config.projects.iter() {
+            for extractor in project.extract.iter() {
+                extractor
+                    .validate()
+                    .wrap_err_with(|| format!("Invalid extractor of {}", project.name))?;
+            }
+        }
+        return Ok(config);
+    }
+
+    /// Loads the included step definitions, remote ones from the configured GitLab with its
+    /// token, and gives the projects that `use` a definition its deploy command and extractors.
+    pub fn resolve_steps(&mut self, token: &str) -> Result<()> {
+        for include in self.include.iter() {
+            let library = load_step_library(include, &self.directory, self.gitlab.as_ref(), token)
+                .wrap_err_with(|| format!("Could not include {}", include))?;
+            for (name, definition) in library.steps {
+                // Definitions in the config itself override included ones
+                self.steps.entry(name).or_insert(definition);
+            }
+        }
+        for (name, definition) in self.steps.iter() {
+            for extractor in definition.extract.iter() {
+                extractor
+                    .validate()
+                    .wrap_err_with(|| format!("Invalid extractor of step {}", name))?;
+            }
+        }
+        for project in self.projects.iter_mut() {
+            let Some(name) = &project.uses else {
+                continue;
+            };
+            let Some(definition) = self.steps.get(name) else {
+                bail!("Unknown step {} used by {}", name, project.name);
+            };
+            if project.deploy_command.is_empty() {
+                project.deploy_command = definition.deploy_command.clone();
+            }
+            if project.extract.is_empty() {
+                project.extract = definition.extract.clone();
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployment::Deployment;
+    use crate::Project;
+
+    #[test]
+    fn selecting_a_project_keeps_the_order_of_dependent_steps() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [[projects]]
+            name = "shop"
+            project_id = 1
+            title = "Shop"
+
+            [[projects]]
+            name = "api"
+            project_id = 2
+            title = "API"
+
+            [[deployment_steps]]
+            type = "release_notes"
+            name = "notes"
+
+            [[deployment_steps]]
+            type = "pipeline"
+            project = "shop"
+            name = "shop"
+
+            [[deployment_steps]]
+            type = "pipeline"
+            project = "api"
+            depends_on = ["notes"]
+
+            [[deployment_steps]]
+            type = "mail"
+            depends_on = ["shop"]
+            "#,
+        )
+        .unwrap();
+        config.select_projects(&["api".to_string()]).unwrap();
+
+        let projects: Vec<Project> = config
+            .projects
+            .iter()
+            .cloned()
+            .map(Project::loading)
+            .collect();
+        let deployment = Deployment::with_steps(&projects, &config.step_list());
+        let labels: Vec<&str> = deployment
+            .steps
+            .iter()
+            .map(|step| step.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "Generate release notes",
+                "Starting API Pipeline",
+                "Send release mail"
+            ]
+        );
+        // The mail waited for the shop pipeline, which waited for the release notes
+        assert_eq!(deployment.steps[2].after, [0]);
+    }
+
+    #[test]
+    fn globs_match_within_or_across_directories() {
+        let assets = glob_regex("assets/**").unwrap();
+        assert!(assets.is_match("assets/app.3f2a.js"));
+        assert!(assets.is_match("assets/fonts/inter.woff2"));
+        assert!(!assets.is_match("index.html"));
+
+        let html = glob_regex("*.html").unwrap();
+        assert!(html.is_match("index.html"));
+        assert!(!html.is_match("blog/post.html"));
+
+        let any_html = glob_regex("**/*.html").unwrap();
+        assert!(any_html.is_match("index.html"));
+        assert!(any_html.is_match("blog/post.html"));
+        assert!(!any_html.is_match("index.html.gz"));
+        // Only * and ? are special
+        assert!(!glob_regex("a+b.txt").unwrap().is_match("aab.txt"));
+    }
+
+    #[test]
+    fn only_the_configured_gitlab_is_trusted_with_the_token() {
+        let gitlab = "https://gitlab.example.com/";
+        assert!(same_origin(gitlab, "https://GitLab.example.com"));
+        assert!(!same_origin(gitlab, "http://gitlab.example.com"));
+        assert!(!same_origin(gitlab, "https://gitlab.example.com:8443"));
+        assert!(!same_origin(gitlab, "https://gitlab.example.com.evil.test"));
+        assert!(!same_origin(gitlab, "https://evil.test"));
+    }
+}
@@ -0,0 +1,1527 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use color_eyre::Result;
+
+use crate::artifacts::{Artifact, ArtifactKind, Artifacts};
+use crate::config::{
+    default_steps, MailConfig, NotificationsConfig, Person, ProjectConfig, ReleaseNotesConfig,
+    StaticSiteConfig, StepConfig, StepType, TimeBoxConfig,
+};
+use crate::gitlab::GitlabClient;
+use crate::identity::Identity;
+use crate::mail::send_release_mail;
+use crate::notifications::{self, Notifier};
+use crate::release_notes::release_notes;
+use crate::static_site;
+use crate::Project;
+
+const PIPELINE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How quickly a running step notices that the deployment was cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a cancelled command may clean up before it is killed for good
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+/// How long a finished command's output may stay open, e.g. by a process it left running
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_secs(2);
+/// How many lines of its output a step keeps, older ones are dropped
+const LOG_LINES: usize = 10_000;
+
+pub struct DeploymentOption {
+    pub value: bool,
+    pub label: String,
+    /// Checking the option has to be confirmed, for options that affect others
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed(String),
+    Skipped,
+    /// Waiting for a `HoldDecision` because the deployment ran past its time box
+    Held,
+    /// Failed because it ran past its timeout
+    TimedOut(String),
+}
+
+impl StepStatus {
+    pub fn is_failed(&self) -> bool {
+        return matches!(self, StepStatus::Failed(_) | StepStatus::TimedOut(_));
+    }
+}
+
+/// How long a step may run and how often it is tried again by itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryPolicy {
+    /// Its command is killed and its pipeline given up after this long
+    pub timeout: Option<Duration>,
+    /// Attempts after the first one before the step pauses as failed
+    pub retries: u32,
+    pub retry_delay: Duration,
+}
+
+/// The error of a step that ran past its timeout.
+#[derive(Debug)]
+struct TimedOut(Duration);
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "Timed out after {}s", self.0.as_secs());
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// How to go on with a deployment that was held.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoldDecision {
+    /// Run the remaining steps without another hold
+    Continue,
+    /// Allow `extension_minutes` more before holding again
+    Extend,
+    /// Fail the held step so none of the remaining steps run
+    Stop,
+}
+
+/// How to go on after a step failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailureDecision {
+    /// Run the failed step again
+    Retry,
+    /// Leave the step out and run the remaining ones
+    Skip,
+    /// Stop the deployment at the failed step
+    Abort,
+}
+
+#[derive(Debug, Clone)]
+pub enum StepKind {
+    ReleaseNotes,
+    ReleaseMail,
+    /// Index into the deployed projects
+    Pipeline(usize),
+    /// Command whose arguments can use what earlier steps produced, optionally for one of the
+    /// deployed projects
+    Shell {
+        command: Vec<String>,
+        project: Option<usize>,
+    },
+    Webhook {
+        url: String,
+        message: String,
+    },
+    /// Index into the deployed projects, without a repository the tag is created through GitLab
+    Tag {
+        project: usize,
+        tag: String,
+        message: String,
+        repository: Option<PathBuf>,
+        remote: String,
+    },
+    StaticSite(StaticSiteConfig),
+}
+
+pub struct DeploymentStep {
+    pub label: String,
+    pub kind: StepKind,
+    /// Index of the option in `Deployment.selected_options` that has to be checked for the step to run
+    pub option: Option<usize>,
+    pub status: StepStatus,
+    /// Progress reported by the running step, e.g. the state of a triggered pipeline
+    pub detail: Option<String>,
+    /// Output of the external command the step runs, line by line, at most `LOG_LINES`
+    pub log: VecDeque<String>,
+    /// How many of the oldest lines were dropped from `log`
+    pub dropped_lines: usize,
+    pub policy: RetryPolicy,
+    /// Indexes of the steps that have to be done before this one starts
+    pub after: Vec<usize>,
+}
+
+enum StepEvent {
+    Status {
+        step: usize,
+        status: StepStatus,
+    },
+    Detail {
+        step: usize,
+        detail: String,
+    },
+    Log {
+        step: usize,
+        line: String,
+    },
+    Artifact {
+        artifact: Artifact,
+    },
+    /// The step failed and waits for a `FailureDecision`
+    Paused {
+        step: usize,
+    },
+}
+
+/// Sends the progress of one step back to the UI thread.
+struct StepReporter<'a> {
+    step: usize,
+    sender: &'a Sender<StepEvent>,
+}
+
+impl StepReporter<'_> {
+    fn detail(&self, detail: String) {
+        let _ = self.sender.send(StepEvent::Detail {
+            step: self.step,
+            detail,
+        });
+    }
+
+    fn log(&self, line: String) {
+        let _ = self.sender.send(StepEvent::Log {
+            step: self.step,
+            line,
+        });
+    }
+}
+
+/// Everything the worker thread needs to run the steps.
+struct ExecutionContext {
+    projects: Vec<Project>,
+    gitlab: Option<GitlabClient>,
+    dry_run: bool,
+    headless: bool,
+    release_notes: ReleaseNotesConfig,
+    mail: Option<MailConfig>,
+    notifications: NotificationsConfig,
+    release_manager: Option<Person>,
+    time_box: Option<TimeBoxConfig>,
+    cancel_pipelines: bool,
+    pipeline_timeout: Option<Duration>,
+    cancelled: Arc<AtomicBool>,
+}
+
+pub struct Deployment {
+    pub selected_options: Vec<DeploymentOption>,
+    pub current_option: usize,
+    pub steps: Vec<DeploymentStep>,
+    pub deployment_running: bool,
+    /// Only report what the steps would do instead of running them
+    pub dry_run: bool,
+    /// No UI owns the terminal, so steps may print to stdout
+    pub headless: bool,
+    pub release_notes: ReleaseNotesConfig,
+    pub mail: Option<MailConfig>,
+    pub notifications: NotificationsConfig,
+    /// Whose turn it is according to the rotation, named in announcements
+    pub release_manager: Option<Person>,
+    pub time_box: Option<TimeBoxConfig>,
+    /// Cancel the GitLab pipeline a cancelled deployment was waiting for
+    pub cancel_pipelines: bool,
+    /// Give up waiting for a pipeline after this long, on top of the step's own timeout
+    pub pipeline_timeout: Option<Duration>,
+    /// Who deploys, when they logged in with OIDC
+    pub identity: Option<Identity>,
+    /// What the steps of the last deployment produced
+    pub artifacts: Artifacts,
+    /// How many lines the log pane is scrolled up from the newest output
+    pub log_scroll: usize,
+    events: Option<Receiver<StepEvent>>,
+    decisions: Option<Sender<HoldDecision>>,
+    failure_decisions: Option<Sender<FailureDecision>>,
+    /// Index of the step waiting for a `FailureDecision`
+    paused: Option<usize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Deployment {
+    /// A deployment with the default steps.
+    pub fn new(projects: &[Project]) -> Self {
+        let configs: Vec<ProjectConfig> = projects
+            .iter()
+            .map(|project| project.config.clone())
+            .collect();
+        return Self::with_steps(projects, &default_steps(&configs));
+    }
+
+    /// A deployment running the configured steps, steps of projects that are not deployed are
+    /// left out.
+    pub fn with_steps(projects: &[Project], configs: &[StepConfig]) -> Self {
+        let mut selected_options: Vec<DeploymentOption> = vec![];
+        let mut steps = vec![];
+        // Steps by name and the one before the current step, as what they stand for
+        let mut named: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut previous: Vec<usize> = vec![];
+        for config in configs.iter() {
+            // What the step waits for, a left out step passes on what it would have waited for
+            let after: Vec<usize> = match &config.depends_on {
+                None => previous.clone(),
+                Some(names) => names
+                    .iter()
+                    .flat_map(|name| named.get(name).cloned().unwrap_or_default())
+                    .collect(),
+            };
+            let Some((kind, label)) = step_kind(projects, &config.kind) else {
+                if let Some(name) = &config.name {
+                    named.insert(name.clone(), after);
+                }
+                continue;
+            };
+            let option = config.option.as_ref().map(|label| {
+                if let Some(index) = selected_options
+                    .iter()
+                    .position(|option| &option.label == label)
+                {
+                    return index;
+                }
+                selected_options.push(DeploymentOption {
+                    value: config.checked,
+                    label: label.clone(),
+                    confirm: config.confirm,
+                });
+                return selected_options.len() - 1;
+            });
+            let label = config.label.clone().unwrap_or(label);
+            let mut step = DeploymentStep::new(&label, kind, option);
+            step.policy = RetryPolicy {
+                timeout: config.timeout_seconds.map(Duration::from_secs),
+                retries: config.retries,
+                retry_delay: Duration::from_secs(config.retry_delay_seconds),
+            };
+            step.after = after;
+            if let Some(name) = &config.name {
+                named.insert(name.clone(), vec![steps.len()]);
+            }
+            previous = vec![steps.len()];
+            steps.push(step);
+        }
+        return Self {
+            selected_options,
+            current_option: 0,
+            steps,
+            deployment_running: false,
+            dry_run: false,
+            headless: false,
+            release_notes: ReleaseNotesConfig::default(),
+            mail: None,
+            notifications: NotificationsConfig::default(),
+            release_manager: None,
+            time_box: None,
+            cancel_pipelines: false,
+            pipeline_timeout: None,
+            identity: None,
+            artifacts: Artifacts::default(),
+            log_scroll: 0,
+            events: None,
+            decisions: None,
+            failure_decisions: None,
+            paused: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+    }
+
+    /// Checks or unchecks the option of the release mail step.
+    pub fn set_release_mail(&mut self, enabled: bool) {
+        let option = self
+            .steps
+            .iter()
+            .find(|step| matches!(step.kind, StepKind::ReleaseMail))
+            .and_then(|step| step.option);
+        if let Some(option) = option {
+            self.selected_options[option].value = enabled;
+        }
+    }
+
+    /// Runs all steps whose option is checked on worker threads, each once the steps it waits
+    /// for are done. After a step failed for good no further steps start.
+    pub fn start(&mut self, projects: &[Project], gitlab: Option<&GitlabClient>) {
+        if self.deployment_running {
+            return;
+        }
+
+        let enabled: Vec<bool> = self
+            .steps
+            .iter()
+            .map(|step| {
+                step.option
+                    .is_none_or(|option| self.selected_options[option].value)
+            })
+            .collect();
+        // Position of every enabled step among the jobs
+        let mut positions = HashMap::new();
+        let mut jobs = vec![];
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            step.detail = None;
+            step.log.clear();
+            step.dropped_lines = 0;
+            if !enabled[index] {
+                step.status = StepStatus::Skipped;
+                continue;
+            }
+            step.status = StepStatus::Pending;
+            positions.insert(index, jobs.len());
+            jobs.push(Job {
+                step: index,
+                kind: step.kind.clone(),
+                label: step.label.clone(),
+                policy: step.policy,
+                after: vec![],
+            });
+        }
+        for job in jobs.iter_mut() {
+            job.after = self
+                .enabled_dependencies(job.step, &enabled)
+                .iter()
+                .map(|step| positions[step])
+                .collect();
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.cancelled = Arc::new(AtomicBool::new(false));
+        let context = ExecutionContext {
+            projects: projects.to_vec(),
+            gitlab: gitlab.cloned(),
+            dry_run: self.dry_run,
+            headless: self.headless,
+            release_notes: self.release_notes.clone(),
+            mail: self.mail.clone(),
+            notifications: self.notifications.clone(),
+            release_manager: self.release_manager.clone(),
+            time_box: self.time_box.clone(),
+            cancel_pipelines: self.cancel_pipelines,
+            pipeline_timeout: self.pipeline_timeout,
+            cancelled: self.cancelled.clone(),
+        };
+        let (decision_sender, decisions) = mpsc::channel();
+        let (failure_sender, failure_decisions) = mpsc::channel();
+        std::thread::spawn(move || {
+            execute(jobs, context, sender, decisions, failure_decisions);
+        });
+        self.events = Some(receiver);
+        self.decisions = Some(decision_sender);
+        self.failure_decisions = Some(failure_sender);
+        self.paused = None;
+        self.artifacts.clear();
+        self.deployment_running = true;
+        self.log_scroll = 0;
+    }
+
+    /// Applies the progress reported by the worker thread, call this before rendering.
+    pub fn update(&mut self) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        loop {
+            match events.try_recv() {
+                Ok(StepEvent::Status { step, status }) => {
+                    if self.paused == Some(step) {
+                        self.paused = None;
+                    }
+                    self.steps[step].status = status;
+                }
+                Ok(StepEvent::Paused { step }) => self.paused = Some(step),
+                Ok(StepEvent::Detail { step, detail }) => self.steps[step].detail = Some(detail),
+                Ok(StepEvent::Log { step, line }) => self.steps[step].push_log(line),
+                Ok(StepEvent::Artifact { artifact }) => self.artifacts.insert(artifact),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.events = None;
+                    self.decisions = None;
+                    self.failure_decisions = None;
+                    self.paused = None;
+                    self.deployment_running = false;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// The step whose output the log pane shows: the running one, otherwise the last one
+    /// that produced output.
+    pub fn log_step(&self) -> Option<&DeploymentStep> {
+        return self
+            .steps
+            .iter()
+            .find(|step| step.status == StepStatus::Running)
+            .or_else(|| self.steps.iter().rev().find(|step| !step.log.is_empty()));
+    }
+
+    pub fn scroll_log_up(&mut self, lines: usize) {
+        let log_length = self.log_step().map_or(0, |step| step.log.len());
+        self.log_scroll = (self.log_scroll + lines).min(log_length);
+    }
+
+    pub fn scroll_log_down(&mut self, lines: usize) {
+        self.log_scroll = self.log_scroll.saturating_sub(lines);
+    }
+
+    /// The step waiting for a decision because the deployment ran past its time box.
+    pub fn held_step(&self) -> Option<&DeploymentStep> {
+        return self
+            .steps
+            .iter()
+            .find(|step| step.status == StepStatus::Held);
+    }
+
+    /// Lets a held deployment go on, be extended or stop.
+    pub fn decide(&mut self, decision: HoldDecision) {
+        if let Some(decisions) = &self.decisions {
+            let _ = decisions.send(decision);
+        }
+    }
+
+    /// The steps the step waits for, a step that does not run stands for the ones it waits
+    /// for itself.
+    fn enabled_dependencies(&self, step: usize, enabled: &[bool]) -> Vec<usize> {
+        let mut dependencies = vec![];
+        for after in self.steps[step].after.iter() {
+            if enabled[*after] {
+                dependencies.push(*after);
+            } else {
+                dependencies.extend(self.enabled_dependencies(*after, enabled));
+            }
+        }
+        return dependencies;
+    }
+
+    /// Index of the failed step waiting to be retried or skipped.
+    pub fn paused_index(&self) -> Option<usize> {
+        return self.paused.filter(|_| self.deployment_running);
+    }
+
+    /// The failed step waiting to be retried or skipped.
+    pub fn paused_step(&self) -> Option<&DeploymentStep> {
+        return self.paused_index().map(|index| &self.steps[index]);
+    }
+
+    /// Retries or skips the paused step, or ends the deployment there.
+    pub fn decide_failure(&mut self, decision: FailureDecision) {
+        if let Some(decisions) = &self.failure_decisions {
+            let _ = decisions.send(decision);
+        }
+    }
+
+    /// Aborts the running step and skips the remaining ones.
+    pub fn cancel(&mut self) {
+        if !self.deployment_running {
+            return;
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+        // A held or paused step is waiting for a decision and would not notice otherwise
+        self.decide(HoldDecision::Stop);
+        self.decide_failure(FailureDecision::Abort);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.cancelled.load(Ordering::Relaxed);
+    }
+
+    /// Warning when the rotation names someone else than who logged in with OIDC, or without
+    /// OIDC the current `$USER`.
+    pub fn out_of_turn_warning(&self) -> Option<String> {
+        let person = self.release_manager.as_ref()?;
+        let Some(identity) = &self.identity else {
+            let user = std::env::var("USER").ok()?;
+            if person.user.as_ref()? == &user {
+                return None;
+            }
+            return Some(format!(
+                "{} is release manager this week, not {}",
+                person.name, user
+            ));
+        };
+        if person.user.is_none() && person.email.is_none() {
+            return None;
+        }
+        let same_user = person.user.as_ref() == Some(&identity.name);
+        let same_email = match (&person.email, &identity.email) {
+            (Some(email), Some(other)) => email.eq_ignore_ascii_case(other),
+            _ => false,
+        };
+        if same_user || same_email {
+            return None;
+        }
+        return Some(format!(
+            "{} is release manager this week, not {}",
+            person.name, identity.name
+        ));
+    }
+
+    pub fn has_failed(&self) -> bool {
+        return self.steps.iter().any(|step| step.status.is_failed());
+    }
+
+    pub fn has_finished(&self) -> bool {
+        return !self.deployment_running
+            && self
+                .steps
+                .iter()
+                .any(|step| step.status == StepStatus::Succeeded);
+    }
+}
+
+impl DeploymentStep {
+    fn new(label: &str, kind: StepKind, option: Option<usize>) -> Self {
+        return Self {
+            label: label.to_string(),
+            kind,
+            option,
+            status: StepStatus::Pending,
+            detail: None,
+            log: VecDeque::new(),
+            dropped_lines: 0,
+            policy: RetryPolicy::default(),
+            after: vec![],
+        };
+    }
+
+    fn push_log(&mut self, line: String) {
+        if self.log.len() == LOG_LINES {
+            self.log.pop_front();
+            self.dropped_lines += 1;
+        }
+        self.log.push_back(line);
+    }
+
+    /// How many lines of output the step had, including the dropped ones.
+    pub fn log_lines(&self) -> usize {
+        return self.dropped_lines + self.log.len();
+    }
+}
+
+/// The step to run for the configured type and its default label, `None` for steps of
+/// projects that are not deployed.
+fn step_kind(projects: &[Project], kind: &StepType) -> Option<(StepKind, String)> {
+    let (kind, label) = match kind {
+        StepType::ReleaseNotes => (StepKind::ReleaseNotes, "Generate release notes".to_string()),
+        StepType::Mail => (StepKind::ReleaseMail, "Send release mail".to_string()),
+        StepType::Pipeline { project } => {
+            let index = projects
+                .iter()
+                .position(|deployed| &deployed.config.name == project)?;
+            let label = format!("Starting {} Pipeline", projects[index].config.title);
+            (StepKind::Pipeline(index), label)
+        }
+        StepType::Shell { command, project } => {
+            let project = match project {
+                Some(project) => {
+                    let index = projects
+                        .iter()
+                        .position(|deployed| &deployed.config.name == project)?;
+                    Some(index)
+                }
+                None => None,
+            };
+            let kind = StepKind::Shell {
+                command: command.clone(),
+                project,
+            };
+            (kind, format!("Run {}", command.join(" ")))
+        }
+        // The URL usually contains a secret
+        StepType::Webhook { url, message } => (
+            StepKind::Webhook {
+                url: url.clone(),
+                message: message.clone(),
+            },
+            "Post to webhook".to_string(),
+        ),
+        StepType::Tag {
+            project,
+            tag,
+            message,
+            repository,
+            remote,
+        } => {
+            let index = projects
+                .iter()
+                .position(|deployed| &deployed.config.name == project)?;
+            let kind = StepKind::Tag {
+                project: index,
+                tag: tag.clone(),
+                message: message.clone(),
+                repository: repository.clone(),
+                remote: remote.clone(),
+            };
+            (
+                kind,
+                format!("Tag {} release", projects[index].config.title),
+            )
+        }
+        StepType::StaticSite(config) => {
+            let label = format!("Upload {} to {}", config.source.display(), config.bucket);
+            (StepKind::StaticSite(config.clone()), label)
+        }
+    };
+    return Some((kind, label));
+}
+
+/// A step of the running deployment.
+struct Job {
+    /// Index into the deployment's steps
+    step: usize,
+    kind: StepKind,
+    label: String,
+    policy: RetryPolicy,
+    /// Positions of the jobs that have to be done first
+    after: Vec<usize>,
+}
+
+/// How a job ended, for the scheduler.
+enum JobOutcome {
+    /// Succeeded, or failed and skipped on purpose, with what it produced
+    Done(Vec<Artifact>),
+    /// Failed for good, the deployment ends once the running jobs are done
+    Failed,
+    /// The deployment was cancelled before the job started
+    NotStarted,
+    /// The deployment was cancelled while the job ran
+    Cancelled,
+}
+
+/// What the jobs running at the same time share.
+struct Execution<'a> {
+    context: &'a ExecutionContext,
+    sender: &'a Sender<StepEvent>,
+    notifier: Option<Notifier>,
+    deployed: Vec<&'a Project>,
+    /// Held and paused jobs take turns waiting for a decision
+    decisions: Mutex<Receiver<HoldDecision>>,
+    failure_decisions: Mutex<Receiver<FailureDecision>>,
+    /// End of the time box, dry runs finish right away so there is nothing to hold
+    deadline: Mutex<Option<Instant>>,
+    started: AtomicBool,
+}
+
+/// Runs the jobs as soon as the ones they wait for are done, independent ones at the same time.
+fn execute(
+    jobs: Vec<Job>,
+    context: ExecutionContext,
+    sender: Sender<StepEvent>,
+    decisions: Receiver<HoldDecision>,
+    failure_decisions: Receiver<FailureDecision>,
+) {
+    let execution = Execution {
+        context: &context,
+        sender: &sender,
+        // Dry runs stay quiet, nothing is actually deployed
+        notifier: (!context.dry_run).then(|| Notifier::new(&context.notifications)),
+        deployed: jobs
+            .iter()
+            .filter_map(|job| match job.kind {
+                StepKind::Pipeline(index) => Some(&context.projects[index]),
+                _ => None,
+            })
+            .collect(),
+        decisions: Mutex::new(decisions),
+        failure_decisions: Mutex::new(failure_decisions),
+        deadline: Mutex::new(
+            context
+                .time_box
+                .as_ref()
+                .filter(|_| !context.dry_run)
+                .map(|time_box| Instant::now() + Duration::from_secs(time_box.max_minutes * 60)),
+        ),
+        started: AtomicBool::new(false),
+    };
+    let mut artifacts = Artifacts::default();
+    let mut done = vec![false; jobs.len()];
+    let mut waiting: Vec<usize> = (0..jobs.len()).collect();
+    let mut failed = false;
+    let mut cancel_announced = false;
+    let (outcome_sender, outcomes) = mpsc::channel();
+    std::thread::scope(|scope| {
+        let mut running = 0;
+        loop {
+            if !failed && !context.cancelled.load(Ordering::Relaxed) {
+                let (ready, blocked): (Vec<usize>, Vec<usize>) = waiting
+                    .iter()
+                    .partition(|position| jobs[**position].after.iter().all(|after| done[*after]));
+                waiting = blocked;
+                for position in ready {
+                    running += 1;
+                    let (job, execution) = (&jobs[position], &execution);
+                    // What the jobs it waited for produced
+                    let artifacts = artifacts.clone();
+                    let outcome_sender = outcome_sender.clone();
+                    scope.spawn(move || {
+                        let outcome = run_job(job, execution, &artifacts);
+                        let _ = outcome_sender.send((position, outcome));
+                    });
+                }
+            }
+            if running == 0 {
+                break;
+            }
+            let Ok((position, outcome)) = outcomes.recv() else {
+                break;
+            };
+            running -= 1;
+            match outcome {
+                JobOutcome::Done(produced) => {
+                    for artifact in produced {
+                        artifacts.insert(artifact);
+                    }
+                    done[position] = true;
+                }
+                JobOutcome::Failed => failed = true,
+                JobOutcome::NotStarted => waiting.push(position),
+                JobOutcome::Cancelled => cancel_announced = true,
+            }
+        }
+    });
+
+    if context.cancelled.load(Ordering::Relaxed) {
+        waiting.sort();
+        if let Some(position) = waiting.first().filter(|_| !cancel_announced) {
+            execution.notify(
+                notifications::cancelled_message(&jobs[*position].label),
+                &execution.reporter(jobs[*position].step),
+            );
+        }
+        skip(waiting.iter().map(|position| jobs[*position].step), &sender);
+        return;
+    }
+    if failed {
+        // The deployment ends at the failed step, what did not start yet never will
+        skip(waiting.iter().map(|position| jobs[*position].step), &sender);
+        return;
+    }
+    if let Some(notifier) = &execution.notifier {
+        // No step is left whose log could show the error
+        let _ = notifier.notify(&notifications::finished_message(
+            &execution.deployed,
+            context.release_manager.as_ref(),
+        ));
+    }
+}
+
+impl Execution<'_> {
+    fn reporter(&self, step: usize) -> StepReporter<'_> {
+        return StepReporter {
+            step,
+            sender: self.sender,
+        };
+    }
+
+    fn notify(&self, message: String, report: &StepReporter) {
+        let Some(notifier) = &self.notifier else {
+            return;
+        };
+        if let Err(error) = notifier.notify(&message) {
+            report.log(format!("Notification failed: {}", error_message(&error)));
+        }
+    }
+
+    fn status(&self, step: usize, status: StepStatus) {
+        let _ = self.sender.send(StepEvent::Status { step, status });
+    }
+
+    /// Holds the job while the deployment is past its time box, returns whether it was stopped.
+    fn hold(&self, job: &Job, report: &StepReporter) -> bool {
+        let decisions = self.decisions.lock().unwrap();
+        loop {
+            let deadline = *self.deadline.lock().unwrap();
+            if deadline.is_none_or(|deadline| Instant::now() <= deadline) {
+                return false;
+            }
+            if self.context.cancelled.load(Ordering::Relaxed) {
+                return true;
+            }
+            self.status(job.step, StepStatus::Held);
+            self.notify(notifications::held_message(&job.label), report);
+            // A closed channel means nobody is left to decide, stop to be safe
+            match decisions.recv().unwrap_or(HoldDecision::Stop) {
+                HoldDecision::Continue => *self.deadline.lock().unwrap() = None,
+                HoldDecision::Extend => {
+                    let minutes = self
+                        .context
+                        .time_box
+                        .as_ref()
+                        .map_or(0, |time_box| time_box.extension_minutes);
+                    *self.deadline.lock().unwrap() =
+                        Some(Instant::now() + Duration::from_secs(minutes * 60));
+                }
+                HoldDecision::Stop => return true,
+            }
+        }
+    }
+
+    /// Pauses the failed job until it is retried, skipped or the deployment ends there.
+    fn decide_failure(&self, job: &Job, failure: &color_eyre::Report) -> FailureDecision {
+        let decisions = self.failure_decisions.lock().unwrap();
+        if self.context.cancelled.load(Ordering::Relaxed) {
+            return FailureDecision::Abort;
+        }
+        self.notify(
+            notifications::failed_message(&job.label, &error_message(failure)),
+            &self.reporter(job.step),
+        );
+        self.status(job.step, failed_status(failure));
+        let _ = self.sender.send(StepEvent::Paused { step: job.step });
+        // A closed channel means nobody is left to decide
+        return decisions.recv().unwrap_or(FailureDecision::Abort);
+    }
+}
+
+/// Runs one job from holding it to announcing how it ended.
+fn run_job(job: &Job, execution: &Execution, artifacts: &Artifacts) -> JobOutcome {
+    let context = execution.context;
+    let (step, kind, label, policy) = (job.step, &job.kind, &job.label, &job.policy);
+    let report = execution.reporter(step);
+    // Cancelled before it started
+    if context.cancelled.load(Ordering::Relaxed) {
+        return JobOutcome::NotStarted;
+    }
+    // Without a version there is nothing to tag, e.g. when every merge request is excluded
+    if let StepKind::Tag { project, .. } = kind {
+        if context.projects[*project].next_version().is_none() {
+            report.detail("nothing released, not tagged".to_string());
+            execution.status(step, StepStatus::Skipped);
+            return JobOutcome::Done(vec![]);
+        }
+    }
+    let irreversible = !matches!(kind, StepKind::ReleaseNotes);
+    let stopped = irreversible && execution.hold(job, &report);
+    execution.status(step, StepStatus::Running);
+    if !stopped && !execution.started.swap(true, Ordering::Relaxed) {
+        execution.notify(
+            notifications::started_message(&execution.deployed, context.release_manager.as_ref()),
+            &report,
+        );
+    }
+    let mut result = if stopped {
+        Err(eyre!("Stopped, the deployment ran past its time box"))
+    } else {
+        run_step(kind, context, artifacts, &report, policy.timeout)
+    };
+    // A failed step is tried again by itself as configured, then waits until it is
+    // retried, skipped or the deployment ends there
+    let mut skipped = false;
+    let mut retried = 0;
+    while let Err(failure) = &result {
+        if stopped || context.cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let error = error_message(failure);
+        if retried < policy.retries {
+            retried += 1;
+            report.log(format!(
+                "{}, retrying in {}s ({} of {})",
+                error,
+                policy.retry_delay.as_secs(),
+                retried,
+                policy.retries
+            ));
+            if wait_until_cancelled(&context.cancelled, policy.retry_delay) {
+                break;
+            }
+            result = run_step(kind, context, artifacts, &report, policy.timeout);
+            continue;
+        }
+        match execution.decide_failure(job, failure) {
+            FailureDecision::Retry => {
+                report.log("Retrying".to_string());
+                execution.status(step, StepStatus::Running);
+                result = run_step(kind, context, artifacts, &report, policy.timeout);
+            }
+            FailureDecision::Skip => {
+                report.detail(format!("failed: {}", error));
+                skipped = true;
+                break;
+            }
+            FailureDecision::Abort => break,
+        }
+    }
+    let cancelled = context.cancelled.load(Ordering::Relaxed);
+    let (status, produced) = match result {
+        Ok(produced) => (StepStatus::Succeeded, produced),
+        Err(_) if skipped => (StepStatus::Skipped, vec![]),
+        Err(_) if cancelled => (StepStatus::Failed("Cancelled".to_string()), vec![]),
+        Err(error) => (failed_status(&error), vec![]),
+    };
+    for artifact in produced.iter() {
+        report.log(format!("Output {}", artifact));
+        let _ = execution.sender.send(StepEvent::Artifact {
+            artifact: artifact.clone(),
+        });
+    }
+    match (&status, kind) {
+        (StepStatus::Failed(_), _) if cancelled => {
+            execution.notify(notifications::cancelled_message(label), &report);
+        }
+        // Steps that were not stopped were announced when they failed
+        (StepStatus::Failed(error), _) if stopped => {
+            execution.notify(notifications::failed_message(label, error), &report);
+        }
+        (StepStatus::Succeeded, StepKind::Pipeline(index)) => execution.notify(
+            notifications::pipeline_finished_message(&context.projects[*index], &produced),
+            &report,
+        ),
+        _ => {}
+    }
+    let failed = status.is_failed();
+    execution.status(step, status);
+    if failed && cancelled {
+        return JobOutcome::Cancelled;
+    }
+    if failed {
+        return JobOutcome::Failed;
+    }
+    return JobOutcome::Done(produced);
+}
+
+/// Marks steps that will not run anymore as skipped.
+fn skip(steps: impl Iterator<Item = usize>, sender: &Sender<StepEvent>) {
+    for step in steps {
+        let _ = sender.send(StepEvent::Status {
+            step,
+            status: StepStatus::Skipped,
+        });
+    }
+}
+
+/// Waits for the given time, returns early with true when the deployment is cancelled.
+fn wait_until_cancelled(cancelled: &AtomicBool, duration: Duration) -> bool {
+    let until = Instant::now() + duration;
+    while Instant::now() < until {
+        if cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        std::thread::sleep(CANCEL_POLL_INTERVAL);
+    }
+    return cancelled.load(Ordering::Relaxed);
+}
+
+/// A timed out step is marked as such, every other error as failed.
+fn failed_status(error: &color_eyre::Report) -> StepStatus {
+    if error.downcast_ref::<TimedOut>().is_some() {
+        return StepStatus::TimedOut(error_message(error));
+    }
+    return StepStatus::Failed(error_message(error));
+}
+
+/// Runs one step with what the earlier steps produced and returns what it produced.
+fn run_step(
+    kind: &StepKind,
+    context: &ExecutionContext,
+    artifacts: &Artifacts,
+    report: &StepReporter,
+    timeout: Option<Duration>,
+) -> Result<Vec<Artifact>> {
+    if context.dry_run {
+        report.detail(describe_step(kind, context));
+        return Ok(vec![]);
+    }
+    match kind {
+        StepKind::ReleaseNotes => {
+            let config = &context.release_notes;
+            let notes = release_notes(&context.projects, config);
+            // Printing would garble the UI, there they go to the step's log
+            if config.path == "-" && context.headless {
+                print!("{}", notes);
+                return Ok(vec![]);
+            }
+            if config.path == "-" {
+                for line in notes.lines() {
+                    report.log(line.to_string());
+                }
+                report.detail("shown in the log".to_string());
+                return Ok(vec![]);
+            }
+            std::fs::write(&config.path, notes)
+                .wrap_err_with(|| format!("Could not write {}", config.path))?;
+            report.detail(config.path.clone());
+            let mut produced = vec![Artifact::new(
+                "release_notes",
+                ArtifactKind::File,
+                &config.path,
+            )];
+            if let Some(url) = &config.url {
+                produced.push(Artifact::new("release_notes_url", ArtifactKind::Url, url));
+            }
+            return Ok(produced);
+        }
+        StepKind::ReleaseMail => {
+            let Some(mail) = &context.mail else {
+                bail!("No [mail] section configured");
+            };
+            let notes = release_notes(&context.projects, &context.release_notes);
+            send_release_mail(
+                mail,
+                &context.projects,
+                &notes,
+                context.release_manager.as_ref(),
+                artifacts.get("release_notes_url"),
+            )?;
+            report.detail(format!("sent to {}", mail.recipients.join(", ")));
+            return Ok(vec![]);
+        }
+        StepKind::Pipeline(index) => {
+            let project = &context.projects[*index];
+            if !project.config.deploy_command.is_empty() {
+                let command =
+                    fill_command(&project.config.deploy_command, Some(project), artifacts);
+                let env = command_env(context, Some(project), artifacts);
+                let output = run_command(&command, &env, timeout, &context.cancelled, report)?;
+                return Ok(extract(project, &output));
+            }
+            let Some(gitlab) = &context.gitlab else {
+                bail!(
+                    "No deploy command or GitLab instance configured for {}",
+                    project.config.title
+                );
+            };
+            let url = run_pipeline(gitlab, project, context, report, timeout)?;
+            let name = format!("{}_pipeline", project.config.name);
+            return Ok(vec![Artifact::new(&name, ArtifactKind::Url, &url)]);
+        }
+        StepKind::Shell { command, project } => {
+            let project = project.map(|index| &context.projects[index]);
+            let command = fill_command(command, project, artifacts);
+            let env = command_env(context, project, artifacts);
+            let output = run_command(&command, &env, timeout, &context.cancelled, report)?;
+            report.detail(format!("exit code 0, {} lines of output", output.len()));
+            return Ok(vec![]);
+        }
+        StepKind::Webhook { url, message } => {
+            let notifier = Notifier::new(&NotificationsConfig {
+                webhooks: vec![url.clone()],
+            });
+            notifier.notify(&artifacts.replace_placeholders(message))?;
+            report.detail("sent".to_string());
+            return Ok(vec![]);
+        }
+        StepKind::Tag {
+            project,
+            tag,
+            message,
+            repository,
+            remote,
+        } => {
+            let project = &context.projects[*project];
+            let name = fill_placeholders(tag, Some(project), artifacts);
+            let message = fill_placeholders(message, Some(project), artifacts);
+            // Only the reviewed commit, whatever was merged since stays out of the release
+            let Some(commit) = project.commit_hash() else {
+                bail!("No changelog for {}, nothing to tag", project.config.title);
+            };
+            match repository {
+                Some(repository) => {
+                    let git = |args: &[&str]| {
+                        let mut command = vec![
+                            "git".to_string(),
+                            "-C".to_string(),
+                            repository.display().to_string(),
+                        ];
+                        command.extend(args.iter().map(|arg| arg.to_string()));
+                        let env = command_env(context, Some(project), artifacts);
+                        return run_command(&command, &env, timeout, &context.cancelled, report);
+                    };
+                    // The local checkout may not have the commit yet
+                    git(&["fetch", "--quiet", remote])?;
+                    git(&["tag", "--annotate", &name, commit, "--message", &message])?;
+                    if let Err(error) = git(&["push", remote, &format!("refs/tags/{}", name)]) {
+                        // Otherwise retrying fails because the tag exists
+                        let _ = git(&["tag", "--delete", &name]);
+                        return Err(error);
+                    }
+                    report.detail(format!("pushed {} to {}", name, remote));
+                }
+                None => {
+                    let Some(gitlab) = &context.gitlab else {
+                        bail!(
+                            "No repository or GitLab instance configured to tag {}",
+                            project.config.title
+                        );
+                    };
+                    let created = gitlab
+                        .create_tag(project.config.project_id, &name, commit, &message)
+                        .wrap_err_with(|| format!("Could not tag {} as {}", commit, name))?;
+                    report.detail(format!("tagged {} as {}", commit, created.name));
+                }
+            }
+            let artifact = format!("{}_tag", project.config.name);
+            return Ok(vec![Artifact::new(
+                &artifact,
+                ArtifactKind::Variable,
+                &name,
+            )]);
+        }
+        StepKind::StaticSite(config) => {
+            let upload = static_site::deploy(config, &context.cancelled, &|line| report.log(line))?;
+            report.detail(upload.to_string());
+            return Ok(vec![]);
+        }
+    }
+}
+
+/// What running the step would do, for dry runs.
+fn describe_step(kind: &StepKind, context: &ExecutionContext) -> String {
+    match kind {
+        StepKind::ReleaseNotes if context.release_notes.path == "-" => {
+            return "would print the release notes".to_string();
+        }
+        StepKind::ReleaseNotes => return format!("would write {}", context.release_notes.path),
+        StepKind::ReleaseMail => match &context.mail {
+            Some(mail) => return format!("would mail {}", mail.recipients.join(", ")),
+            None => return "no [mail] section configured".to_string(),
+        },
+        StepKind::Pipeline(index) => {
+            let project = &context.projects[*index];
+            if !project.config.deploy_command.is_empty() {
+                let command = fill_command(
+                    &project.config.deploy_command,
+                    Some(project),
+                    &Artifacts::default(),
+                );
+                return format!("would run {}", command.join(" "));
+            }
+            let git_ref = project
+                .config
+                .pipeline_ref
+                .as_deref()
+                .unwrap_or("the default branch");
+            return format!(
+                "would trigger a pipeline for project {} on {}",
+                project.config.project_id, git_ref
+            );
+        }
+        StepKind::Shell { command, project } => {
+            let project = project.map(|index| &context.projects[index]);
+            let command = fill_command(command, project, &Artifacts::default());
+            return format!("would run {}", command.join(" "));
+        }
+        StepKind::Webhook { message, .. } => return format!("would post \"{}\"", message),
+        StepKind::Tag {
+            project,
+            tag,
+            repository,
+            remote,
+            ..
+        } => {
+            let project = &context.projects[*project];
+            let name = fill_placeholders(tag, Some(project), &Artifacts::default());
+            let commit = project.commit_hash().unwrap_or("the reviewed commit");
+            return match repository {
+                Some(repository) => format!(
+                    "would tag {} in {} as {} and push it to {}",
+                    commit,
+                    repository.display(),
+                    name,
+                    remote
+                ),
+                None => format!(
+                    "would tag {} of project {} as {}",
+                    commit, project.config.project_id, name
+                ),
+            };
+        }
+        StepKind::StaticSite(config) => {
+            let files = static_site::site_files(&config.source).map_or(0, |files| files.len());
+            let mut description = format!(
+                "would upload {} files to s3://{}/{}",
+                files, config.bucket, config.prefix
+            );
+            if config.delete {
+                description += " and remove the others there";
+            }
+            if let Some(distribution) = &config.distribution_id {
+                description += &format!(", then invalidate {}", distribution);
+            }
+            return description;
+        }
+    }
+}
+
+/// Triggers a pipeline for the project and waits until it finished, returns its URL. Gives up
+/// after the step's timeout or the pipeline timeout, whichever is shorter.
+fn run_pipeline(
+    gitlab: &GitlabClient,
+    project: &Project,
+    context: &ExecutionContext,
+    report: &StepReporter,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    let project_id = project.config.project_id;
+    let git_ref = match &project.config.pipeline_ref {
+        Some(git_ref) => git_ref.clone(),
+        None => gitlab.default_branch(project_id)?,
+    };
+    let mut pipeline = gitlab
+        .create_pipeline(project_id, &git_ref)
+        .wrap_err_with(|| format!("Could not trigger a pipeline on {}", git_ref))?;
+    let timeout = timeout.into_iter().chain(context.pipeline_timeout).min();
+    let started = Instant::now();
+    loop {
+        report.detail(format!("{} {}", pipeline.status, pipeline.web_url));
+        if pipeline.is_finished() {
+            break;
+        }
+        let remaining = timeout.map_or(PIPELINE_POLL_INTERVAL, |timeout| {
+            timeout.saturating_sub(started.elapsed())
+        });
+        let cancelled =
+            wait_until_cancelled(&context.cancelled, PIPELINE_POLL_INTERVAL.min(remaining));
+        let timed_out = timeout.filter(|timeout| started.elapsed() >= *timeout);
+        if cancelled || timed_out.is_some() {
+            if context.cancel_pipelines {
+                gitlab
+                    .cancel_pipeline(project_id, pipeline.id)
+                    .wrap_err("Could not cancel the pipeline")?;
+                report.detail(format!("canceled {}", pipeline.web_url));
+            }
+            if let Some(timeout) = timed_out {
+                return Err(TimedOut(timeout).into());
+            }
+            bail!("Cancelled");
+        }
+        pipeline = gitlab.pipeline(project_id, pipeline.id)?;
+    }
+    if !pipeline.is_successful() {
+        bail!("Pipeline {}", pipeline.status);
+    }
+    return Ok(pipeline.web_url);
+}
+
+/// The command with the project's placeholders replaced, and what earlier steps produced.
+fn fill_command(
+    command: &[String],
+    project: Option<&Project>,
+    artifacts: &Artifacts,
+) -> Vec<String> {
+    return command
+        .iter()
+        .map(|arg| fill_placeholders(arg, project, artifacts))
+        .collect();
+}
+
+fn fill_placeholders(text: &str, project: Option<&Project>, artifacts: &Artifacts) -> String {
+    let mut text = text.to_string();
+    if let Some(project) = project {
+        text = text
+            .replace("{project_id}", &project.config.project_id.to_string())
+            .replace("{version}", &next_version(project));
+    }
+    return artifacts.replace_placeholders(&text);
+}
+
+/// The version the project is released as, empty when nothing of it is released.
+fn next_version(project: &Project) -> String {
+    return project
+        .next_version()
+        .map_or(String::new(), |version| version.to_string());
+}
+
+/// Environment of deploy and shell commands, so scripts need no arguments: `DEPLOY_VERSIONS`
+/// (`name=version` of every project), `DEPLOY_RELEASE_NOTES` once they were written and, for a
+/// project, `DEPLOY_PROJECT`, `DEPLOY_PROJECT_ID` and `DEPLOY_VERSION`.
+fn command_env(
+    context: &ExecutionContext,
+    project: Option<&Project>,
+    artifacts: &Artifacts,
+) -> Vec<(String, String)> {
+    let versions = context
+        .projects
+        .iter()
+        .filter(|project| project.next_version().is_some())
+        .map(|project| format!("{}={}", project.config.name, next_version(project)))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let mut env = vec![("DEPLOY_VERSIONS".to_string(), versions)];
+    if let Some(path) = artifacts.get("release_notes") {
+        env.push(("DEPLOY_RELEASE_NOTES".to_string(), path.to_string()));
+    }
+    if let Some(project) = project {
+        env.push(("DEPLOY_PROJECT".to_string(), project.config.name.clone()));
+        env.push((
+            "DEPLOY_PROJECT_ID".to_string(),
+            project.config.project_id.to_string(),
+        ));
+        env.push(("DEPLOY_VERSION".to_string(), next_version(project)));
+    }
+    return env;
+}
+
+/// The values the project's extractors capture from the deploy command's stdout.
+fn extract(project: &Project, output: &[String]) -> Vec<Artifact> {
+    return project
+        .config
+        .extract
+        .iter()
+        .filter_map(|extractor| {
+            let value = output
+                .iter()
+                .rev()
+                .find_map(|line| extractor.extract(line))?;
+            return Some(Artifact::new(
+                &extractor.name,
+                ArtifactKind::Variable,
+                &value,
+            ));
+        })
+        .collect();
+}
+
+/// Runs the command and streams its stdout and stderr into the step's log, returns the stdout
+/// lines. The command is killed when it runs past the timeout.
+fn run_command(
+    command: &[String],
+    env: &[(String, String)],
+    timeout: Option<Duration>,
+    cancelled: &AtomicBool,
+    report: &StepReporter,
+) -> Result<Vec<String>> {
+    let Some((command, args)) = command.split_first() else {
+        bail!("No command configured");
+    };
+    let mut process = std::process::Command::new(command);
+    process
+        .args(args)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        // In its own process group, reading the terminal would stop it
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // Its own process group, so cancelling also kills what a deploy script started
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    let mut child = process
+        .spawn()
+        .wrap_err_with(|| format!("Could not run {}", command))?;
+    let started = Instant::now();
+    let mut timed_out = false;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    // Not scoped, a background process the command started may keep its output open. Taking
+    // the sender lets the deployment finish without those readers.
+    let sender = Arc::new(Mutex::new(Some(report.sender.clone())));
+    let output = Arc::new(Mutex::new(vec![]));
+    // The last stderr line ends up in the error message if the command fails
+    let last_error = Arc::new(Mutex::new(String::new()));
+    let output_reader = {
+        let output = Arc::clone(&output);
+        spawn_reader(stdout, report.step, &sender, move |line| {
+            output.lock().unwrap().push(line);
+        })
+    };
+    let errors_reader = {
+        let last_error = Arc::clone(&last_error);
+        spawn_reader(stderr, report.step, &sender, move |line| {
+            *last_error.lock().unwrap() = line;
+        })
+    };
+    // Killing the command closes its output, which ends the readers. Until they ended
+    // something in the process group may still hold it open.
+    let mut status = None;
+    let mut exited_at: Option<Instant> = None;
+    let mut terminated_at: Option<Instant> = None;
+    let mut killed_at: Option<Instant> = None;
+    let status = loop {
+        if status.is_none() {
+            match child.try_wait() {
+                Ok(Some(exited)) => status = Some(Ok(exited)),
+                Ok(None) => {}
+                Err(error) => status = Some(Err(error)),
+            }
+        }
+        if let Some(exited_with) = status.take() {
+            let exited = *exited_at.get_or_insert_with(Instant::now);
+            if output_reader.is_finished() && errors_reader.is_finished() {
+                break exited_with;
+            }
+            // E.g. a service the script restarted in the background, it keeps running
+            let left_running = terminated_at.is_none() && exited.elapsed() > OUTPUT_GRACE_PERIOD;
+            // Only something that left the process group can still hold the output
+            let escaped = killed_at.is_some_and(|killed| killed.elapsed() > OUTPUT_GRACE_PERIOD);
+            if left_running || escaped {
+                report.log("(the output of processes left running is not shown)".to_string());
+                break exited_with;
+            }
+            status = Some(exited_with);
+        }
+        let overdue = timeout.is_some_and(|timeout| started.elapsed() > timeout);
+        if terminated_at.is_none() && (cancelled.load(Ordering::Relaxed) || overdue) {
+            timed_out = overdue && !cancelled.load(Ordering::Relaxed);
+            signal_group(&mut child, "TERM");
+            terminated_at = Some(Instant::now());
+        } else if killed_at.is_none()
+            && terminated_at.is_some_and(|terminated| terminated.elapsed() > KILL_GRACE_PERIOD)
+        {
+            signal_group(&mut child, "KILL");
+            killed_at = Some(Instant::now());
+        }
+        std::thread::sleep(CANCEL_POLL_INTERVAL);
+    };
+    sender.lock().unwrap().take();
+    let output = std::mem::take(&mut *output.lock().unwrap());
+    let last_error = last_error.lock().unwrap().clone();
+    let status = status.wrap_err_with(|| format!("Could not run {}", command))?;
+    if cancelled.load(Ordering::Relaxed) {
+        bail!("Cancelled");
+    }
+    if let Some(timeout) = timeout.filter(|_| timed_out) {
+        return Err(TimedOut(timeout).into());
+    }
+    if !status.success() {
+        return Err(eyre!("{} exited with {}: {}", command, status, last_error));
+    }
+    return Ok(output);
+}
+
+/// Logs every line of the output and hands it to `line_read`, until the step stopped waiting
+/// for the output and took the sender.
+fn spawn_reader(
+    output: impl Read + Send + 'static,
+    step: usize,
+    sender: &Arc<Mutex<Option<Sender<StepEvent>>>>,
+    mut line_read: impl FnMut(String) + Send + 'static,
+) -> std::thread::JoinHandle<()> {
+    let sender = Arc::clone(sender);
+    // Reading on, so a process that still writes to it does not get SIGPIPE
+    return std::thread::spawn(move || {
+        for line in read_lines(output) {
+            let sender = sender.lock().unwrap();
+            if let Some(sender) = sender.as_ref() {
+                StepReporter { step, sender }.log(line.clone());
+                line_read(line);
+            }
+        }
+    });
+}
+
+/// Sends the signal to the command and everything else in its process group, elsewhere
+/// only the command itself is killed.
+fn signal_group(child: &mut Child, signal: &str) {
+    #[cfg(unix)]
+    let _ = std::process::Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg("--")
+        .arg(format!("-{}", child.id()))
+        .status();
+    #[cfg(not(unix))]
+    {
+        let _ = signal;
+        let _ = child.kill();
+    }
+}
+
+fn read_lines(output: impl Read) -> impl Iterator<Item = String> {
+    return BufReader::new(output).lines().map_while(|line| line.ok());
+}
+
+/// The error and all its causes on one line, so it fits into the step list.
+fn error_message(error: &color_eyre::Report) -> String {
+    return error
+        .chain()
+        .map(|cause| cause.to_string())
+        .collect::<Vec<String>>()
+        .join(": ");
+}
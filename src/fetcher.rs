@@ -0,0 +1,64 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::{get_changelog_info, Changelog, ChangelogError};
+
+pub struct ChangelogUpdate {
+    /// Index into the configured projects
+    pub project: usize,
+    pub changelog: Result<Changelog, ChangelogError>,
+}
+
+/// Fetches changelogs on background threads so the UI stays responsive while the
+/// changelog commands or API requests run.
+pub struct ChangelogFetcher {
+    config: Arc<Config>,
+    token: String,
+    sender: Sender<ChangelogUpdate>,
+    receiver: Receiver<ChangelogUpdate>,
+}
+
+impl ChangelogFetcher {
+    pub fn new(config: Arc<Config>, token: &str) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        return Self {
+            config,
+            token: token.to_string(),
+            sender,
+            receiver,
+        };
+    }
+
+    pub fn fetch(&self, project: usize) {
+        let config = Arc::clone(&self.config);
+        let token = self.token.clone();
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let project_config = &config.projects[project];
+            let changelog =
+                get_changelog_info(&config, project_config, &token).map(|mut changelog| {
+                    changelog.fill_columns(&config.columns, project_config);
+                    return changelog;
+                });
+            let _ = sender.send(ChangelogUpdate { project, changelog });
+        });
+    }
+
+    pub fn fetch_all(&self) {
+        for project in 0..self.config.projects.len() {
+            self.fetch(project);
+        }
+    }
+
+    /// Changelogs that arrived since the last call, without blocking.
+    pub fn received(&self) -> Vec<ChangelogUpdate> {
+        return self.receiver.try_iter().collect();
+    }
+
+    /// Blocks until the next changelog arrives.
+    pub fn wait(&self) -> ChangelogUpdate {
+        // The fetcher holds a sender itself, so the channel never disconnects
+        return self.receiver.recv().unwrap();
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::{bail, WrapErr};
+use color_eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::{Changelog, CurrentCommit, MergeRequest};
+
+const PER_PAGE: &str = "100";
+
+/// Minimal client for the GitLab REST API (v4), authenticated with a personal access token.
+/// Without a token (an empty one) the requests are anonymous.
+#[derive(Clone)]
+pub struct GitlabClient {
+    base_url: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    commit: Commit,
+}
+
+#[derive(Debug, Deserialize)]
+struct Commit {
+    id: String,
+    title: String,
+    author_name: String,
+    committed_date: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Pipeline {
+    pub id: u64,
+    pub status: String,
+    pub web_url: String,
+}
+
+impl Pipeline {
+    /// Whether the pipeline stopped running, successfully or not
+    pub fn is_finished(&self) -> bool {
+        return matches!(
+            self.status.as_str(),
+            "success" | "failed" | "canceled" | "skipped"
+        );
+    }
+
+    pub fn is_successful(&self) -> bool {
+        return self.status == "success";
+    }
+}
+
+/// An entry of the user's GitLab to-do list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Todo {
+    pub action_name: String,
+    pub target_url: String,
+    pub body: String,
+    pub project: Option<TodoProject>,
+    pub author: TodoAuthor,
+    pub target: Option<TodoTarget>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TodoProject {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TodoAuthor {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TodoTarget {
+    pub title: Option<String>,
+}
+
+impl Todo {
+    /// Why the to-do was created, e.g. "Approval requested".
+    pub fn reason(&self) -> &str {
+        match self.action_name.as_str() {
+            "assigned" => return "Assigned",
+            "mentioned" | "directly_addressed" => return "Mentioned",
+            "build_failed" => return "Pipeline failed",
+            "approval_required" => return "Approval requested",
+            "review_requested" => return "Review requested",
+            "unmergeable" => return "Cannot be merged",
+            "marked" => return "Marked as to-do",
+            other => return other,
+        }
+    }
+
+    /// Title of the merge request or issue, the to-do text if there is none.
+    pub fn title(&self) -> &str {
+        return self
+            .target
+            .as_ref()
+            .and_then(|target| target.title.as_deref())
+            .unwrap_or(&self.body);
+    }
+}
+
+/// Review evidence of a merge request.
+#[derive(Debug, Clone)]
+pub struct MergeRequestApprovals {
+    /// Names of everyone who approved
+    pub approved_by: Vec<String>,
+    /// Whether all code owner rules are approved, `None` without code owner rules (or on tiers
+    /// without approval rules)
+    pub code_owners_approved: Option<bool>,
+    pub unresolved_discussions: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiApprovals {
+    approved_by: Vec<ApiApprover>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiApprover {
+    user: TodoAuthor,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiApprovalState {
+    rules: Vec<ApiApprovalRule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiApprovalRule {
+    rule_type: String,
+    approved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiDiscussion {
+    notes: Vec<ApiNote>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiNote {
+    #[serde(default)]
+    resolvable: bool,
+    #[serde(default)]
+    resolved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMergeRequest {
+    iid: u64,
+    title: String,
+    web_url: String,
+    source_branch: String,
+    labels: Vec<String>,
+    merged_at: Option<String>,
+    description: Option<String>,
+}
+
+impl GitlabClient {
+    pub fn new(base_url: &str, token: &str) -> Self {
+        return Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            agent: ureq::AgentBuilder::new().build(),
+        };
+    }
+
+    fn authenticate(&self, request: ureq::Request) -> ureq::Request {
+        if self.token.is_empty() {
+            return request;
+        }
+        return request.set("PRIVATE-TOKEN", &self.token);
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        return self.request("GET", path, query);
+    }
+
+    fn post<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        return self.request("POST", path, query);
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        let url = format!("{}/api/v4{}", self.base_url, path);
+        let mut request = self.authenticate(self.agent.request(method, &url));
+        for (key, value) in query {
+            request = request.query(key, value);
+        }
+        let response = request
+            .call()
+            .wrap_err_with(|| format!("GitLab request to {} failed", url))?;
+        return response
+            .into_json()
+            .wrap_err_with(|| format!("Unexpected response from {}", url));
+    }
+
+    pub fn default_branch(&self, project_id: u64) -> Result<String> {
+        let project: Project = self.get(&format!("/projects/{}", project_id), &[])?;
+        return Ok(project.default_branch);
+    }
+
+    pub fn create_pipeline(&self, project_id: u64, git_ref: &str) -> Result<Pipeline> {
+        return self.post(
+            &format!("/projects/{}/pipeline", project_id),
+            &[("ref", git_ref)],
+        );
+    }
+
+    pub fn pipeline(&self, project_id: u64, pipeline_id: u64) -> Result<Pipeline> {
+        return self.get(
+            &format!("/projects/{}/pipelines/{}", project_id, pipeline_id),
+            &[],
+        );
+    }
+
+    pub fn cancel_pipeline(&self, project_id: u64, pipeline_id: u64) -> Result<Pipeline> {
+        return self.post(
+            &format!("/projects/{}/pipelines/{}/cancel", project_id, pipeline_id),
+            &[],
+        );
+    }
+
+    /// Creates an annotated tag on the branch, tag or commit.
+    pub fn create_tag(
+        &self,
+        project_id: u64,
+        name: &str,
+        git_ref: &str,
+        message: &str,
+    ) -> Result<Tag> {
+        return self.post(
+            &format!("/projects/{}/repository/tags", project_id),
+            &[("tag_name", name), ("ref", git_ref), ("message", message)],
+        );
+    }
+
+    /// Contents of a file in a repository at the given branch, tag or commit.
+    pub fn repository_file(&self, project: &str, file: &str, git_ref: &str) -> Result<String> {
+        let url = format!(
+            "{}/api/v4/projects/{}/repository/files/{}/raw",
+            self.base_url,
+            project.replace('/', "%2F"),
+            file.replace('/', "%2F")
+        );
+        let response = self
+            .authenticate(self.agent.get(&url))
+            .query("ref", git_ref)
+            .call()
+            .wrap_err_with(|| format!("GitLab request to {} failed", url))?;
+        return response
+            .into_string()
+            .wrap_err_with(|| format!("Unexpected response from {}", url));
+    }
+
+    /// Who approved the merge request behind a web link, whether its code owners approved and
+    /// how many discussions are still unresolved.
+    pub fn approvals(&self, link: &str) -> Result<MergeRequestApprovals> {
+        let Some((project, iid)) = self.merge_request_path(link) else {
+            bail!("{} is not a merge request on {}", link, self.base_url);
+        };
+        let path = format!(
+            "/projects/{}/merge_requests/{}",
+            project.replace('/', "%2F"),
+            iid
+        );
+        let approvals: ApiApprovals = self.get(&format!("{}/approvals", path), &[])?;
+        // Approval rules need a paid tier, without them there is nothing to check
+        let code_owners_approved = self
+            .get::<ApiApprovalState>(&format!("{}/approval_state", path), &[])
+            .ok()
+            .and_then(|state| {
+                let rules: Vec<ApiApprovalRule> = state
+                    .rules
+                    .into_iter()
+                    .filter(|rule| rule.rule_type == "code_owner")
+                    .collect();
+                if rules.is_empty() {
+                    return None;
+                }
+                return Some(rules.iter().all(|rule| rule.approved));
+            });
+
+        let mut unresolved_discussions = 0;
+        let mut page = 1;
+        loop {
+            let page_string = page.to_string();
+            let batch: Vec<ApiDiscussion> = self.get(
+                &format!("{}/discussions", path),
+                &[("per_page", PER_PAGE), ("page", page_string.as_str())],
+            )?;
+            unresolved_discussions += batch
+                .iter()
+                .filter(|discussion| {
+                    discussion
+                        .notes
+                        .iter()
+                        .any(|note| note.resolvable && !note.resolved)
+                })
+                .count();
+            if batch.len() < PER_PAGE.parse().unwrap() {
+                break;
+            }
+            page += 1;
+        }
+
+        return Ok(MergeRequestApprovals {
+            approved_by: approvals
+                .approved_by
+                .into_iter()
+                .map(|approver| approver.user.name)
+                .collect(),
+            code_owners_approved,
+            unresolved_discussions,
+        });
+    }
+
+    /// Project path and iid of a link like `<base url>/group/project/-/merge_requests/12`.
+    fn merge_request_path(&self, link: &str) -> Option<(String, u64)> {
+        let path = link.strip_prefix(&self.base_url)?.trim_start_matches('/');
+        let (project, rest) = path.split_once("/-/merge_requests/")?;
+        let iid = rest.split(['/', '#', '?']).next()?.parse().ok()?;
+        return Some((project.to_string(), iid));
+    }
+
+    /// Pending to-dos of the token's user.
+    pub fn todos(&self) -> Result<Vec<Todo>> {
+        return self.get("/todos", &[("state", "pending"), ("per_page", PER_PAGE)]);
+    }
+
+    /// Builds the changelog of everything merged into the default branch since the latest tag.
+    pub fn changelog(&self, project_id: u64) -> Result<Changelog> {
+        let default_branch = self.default_branch(project_id)?;
+        let latest_tag = self.latest_tag(project_id)?;
+        let head: Vec<Commit> = self.get(
+            &format!("/projects/{}/repository/commits", project_id),
+            &[("ref_name", &default_branch), ("per_page", "1")],
+        )?;
+        let head = head
+            .into_iter()
+            .next()
+            .ok_or_else(|| color_eyre::eyre::eyre!("Project {} has no commits", project_id))?;
+
+        let since = latest_tag
+            .as_ref()
+            .map(|tag| tag.commit.committed_date.clone());
+        let merge_requests = self
+            .merged_since(project_id, &default_branch, since.as_deref())?
+            .into_iter()
+            .map(|merge_request| {
+                return MergeRequest {
+                    ticket_number: ticket_number(&merge_request),
+                    title: merge_request.title,
+                    github: merge_request.web_url,
+                    flags: merge_request.labels.clone(),
+                    description: merge_request.description.unwrap_or_default(),
+                    columns: BTreeMap::new(),
+                };
+            })
+            .collect();
+
+        return Ok(Changelog {
+            next_version_number: latest_tag.map_or(1, |tag| version_number(&tag.name) + 1),
+            commit: CurrentCommit {
+                commit_hash: head.id,
+                title: head.title,
+                author_name: head.author_name,
+            },
+            current_time: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            merge_requests,
+        });
+    }
+
+    fn latest_tag(&self, project_id: u64) -> Result<Option<Tag>> {
+        let tags: Vec<Tag> = self.get(
+            &format!("/projects/{}/repository/tags", project_id),
+            &[("order_by", "updated"), ("per_page", "1")],
+        )?;
+        return Ok(tags.into_iter().next());
+    }
+
+    fn merged_since(
+        &self,
+        project_id: u64,
+        branch: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<ApiMergeRequest>> {
+        let mut merge_requests = vec![];
+        let mut page = 1;
+        loop {
+            let page_string = page.to_string();
+            let mut query = vec![
+                ("state", "merged"),
+                ("target_branch", branch),
+                ("per_page", PER_PAGE),
+                ("page", page_string.as_str()),
+            ];
+            if let Some(since) = since {
+                query.push(("updated_after", since));
+            }
+            let batch: Vec<ApiMergeRequest> =
+                self.get(&format!("/projects/{}/merge_requests", project_id), &query)?;
+            let is_last_page = batch.len() < PER_PAGE.parse().unwrap();
+            merge_requests.extend(batch);
+            if is_last_page {
+                break;
+            }
+            page += 1;
+        }
+
+        // updated_after also matches MRs merged earlier and touched since, compare the merge date
+        if let Some(since) = since {
+            let since = chrono::DateTime::parse_from_rfc3339(since)
+                .wrap_err_with(|| format!("Invalid tag date {}", since))?;
+            merge_requests.retain(|merge_request| {
+                return merge_request
+                    .merged_at
+                    .as_deref()
+                    .and_then(|merged_at| chrono::DateTime::parse_from_rfc3339(merged_at).ok())
+                    .is_some_and(|merged_at| merged_at > since);
+            });
+        }
+        return Ok(merge_requests);
+    }
+}
+
+/// Ticket key like "SHOP-123" from the branch or title, "!<iid>" when there is none.
+fn ticket_number(merge_request: &ApiMergeRequest) -> String {
+    return find_ticket(&merge_request.source_branch)
+        .or_else(|| find_ticket(&merge_request.title))
+        .unwrap_or_else(|| format!("!{}", merge_request.iid));
+}
+
+fn find_ticket(text: &str) -> Option<String> {
+    for word in text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-')) {
+        if let Some((key, number)) = word.split_once('-') {
+            let number: String = number.chars().take_while(|c| c.is_ascii_digit()).collect();
+            let is_key = key.len() > 1
+                && key.starts_with(|c: char| c.is_ascii_alphabetic())
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+            if is_key && !number.is_empty() {
+                return Some(format!("{}-{}", key, number));
+            }
+        }
+    }
+    return None;
+}
+
+/// "v251" -> 251
+fn version_number(tag: &str) -> u32 {
+    let digits: String = tag
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    return digits.parse().unwrap_or(0);
+}
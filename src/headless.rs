@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+
+use crate::approvals;
+use crate::deployment::{Deployment, DeploymentStep, FailureDecision, HoldDecision, StepStatus};
+use crate::gitlab::GitlabClient;
+use crate::history::{DeploymentRecord, History};
+use crate::Project;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runs the deployment without the UI and prints every step's progress to stdout.
+/// Fails if any step failed, so cron jobs and CI pipelines notice.
+pub fn deploy(
+    deployment: &mut Deployment,
+    projects: &[Project],
+    gitlab: Option<&GitlabClient>,
+    history: Option<&mut History>,
+) -> Result<()> {
+    if let Some(warning) = deployment.out_of_turn_warning() {
+        println!("Warning: {}", warning);
+    }
+    // The commands run in their own process groups, so Ctrl+C only reaches this process
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_interrupted = Arc::clone(&interrupted);
+    let _ = ctrlc::set_handler(move || {
+        // A second Ctrl+C does not wait for the cancelled steps
+        if handler_interrupted.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    });
+    deployment.headless = true;
+    deployment.start(projects, gitlab);
+    let mut reported: Vec<(StepStatus, Option<String>, usize)> = deployment
+        .steps
+        .iter()
+        .map(|step| (StepStatus::Pending, step.detail.clone(), 0))
+        .collect();
+    loop {
+        deployment.update();
+        for (step, last) in deployment.steps.iter().zip(reported.iter_mut()) {
+            let status_changed = step.status != last.0;
+            // A step that already finished prints its output before the result
+            if status_changed && step.status == StepStatus::Running {
+                print_status(step);
+            }
+            if step.detail != last.1 {
+                if let Some(detail) = &step.detail {
+                    println!("          {}", detail);
+                }
+                last.1 = step.detail.clone();
+            }
+            let unprinted = step.log_lines() - last.2;
+            if unprinted > step.log.len() {
+                println!("          ({} lines dropped)", unprinted - step.log.len());
+            }
+            for line in step
+                .log
+                .iter()
+                .skip(step.log.len().saturating_sub(unprinted))
+            {
+                println!("          | {}", line);
+            }
+            last.2 = step.log_lines();
+            if status_changed && step.status != StepStatus::Running {
+                print_status(step);
+            }
+            last.0 = step.status.clone();
+        }
+        if interrupted.load(Ordering::Relaxed) && !deployment.is_cancelled() {
+            println!("Interrupted, cancelling the deployment (Ctrl+C again to quit right away)");
+            deployment.cancel();
+        }
+        // Nobody can decide without a UI, stop before anything irreversible happens
+        if deployment.held_step().is_some() {
+            deployment.decide(HoldDecision::Stop);
+        }
+        if deployment.paused_step().is_some() {
+            deployment.decide_failure(FailureDecision::Abort);
+        }
+        if !deployment.deployment_running {
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    if !deployment.artifacts.is_empty() {
+        println!("Produced:");
+        for artifact in deployment.artifacts.iter() {
+            println!("          {}", artifact);
+        }
+    }
+    if let Some(history) = history {
+        let approvals = match gitlab {
+            Some(gitlab) => approvals::load(gitlab, approvals::included(projects)),
+            None => vec![],
+        };
+        history.record(DeploymentRecord::new(deployment, projects, &approvals))?;
+    }
+    if deployment.has_failed() {
+        bail!("Deployment failed");
+    }
+    return Ok(());
+}
+
+fn print_status(step: &DeploymentStep) {
+    match &step.status {
+        StepStatus::Pending => {}
+        StepStatus::Running => println!("[running] {}", step.label),
+        StepStatus::Succeeded => println!("[done]    {}", step.label),
+        StepStatus::Failed(error) => println!("[failed]  {}: {}", step.label, error),
+        StepStatus::TimedOut(error) => println!("[timeout] {}: {}", step.label, error),
+        StepStatus::Skipped => println!("[skipped] {}", step.label),
+        StepStatus::Held => println!("[held]    {}: ran past the time box", step.label),
+    }
+}
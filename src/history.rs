@@ -0,0 +1,320 @@
+use std::path::PathBuf;
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::approvals::ApprovalSummary;
+use crate::artifacts::Artifact;
+use crate::deployment::{Deployment, StepKind, StepStatus};
+use crate::release_notes::release_notes;
+use crate::storage::HistoryStore;
+use crate::Project;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleasedVersion {
+    pub project: String,
+    pub version: u32,
+    /// Name of the tag a tag step created for the release
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Label of the step that deployed the project, `None` in records from before it was kept
+    #[serde(default)]
+    pub step: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub label: String,
+    /// succeeded, failed: <error>, skipped or not run
+    pub outcome: String,
+}
+
+/// Review evidence of a released merge request, as it was when the deployment finished.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedApproval {
+    pub project: String,
+    pub ticket: String,
+    pub title: String,
+    pub link: String,
+    pub approved_by: Vec<String>,
+    /// `None` without code owner rules
+    pub code_owners_approved: Option<bool>,
+    pub unresolved_discussions: usize,
+    /// Why the approvals could not be loaded
+    pub error: Option<String>,
+}
+
+impl From<&ApprovalSummary> for RecordedApproval {
+    fn from(summary: &ApprovalSummary) -> Self {
+        let mut recorded = Self {
+            project: summary.project.clone(),
+            ticket: summary.ticket.clone(),
+            title: summary.title.clone(),
+            link: summary.link.clone(),
+            approved_by: vec![],
+            code_owners_approved: None,
+            unresolved_discussions: 0,
+            error: None,
+        };
+        match &summary.approvals {
+            Ok(approvals) => {
+                recorded.approved_by = approvals.approved_by.clone();
+                recorded.code_owners_approved = approvals.code_owners_approved;
+                recorded.unresolved_discussions = approvals.unresolved_discussions;
+            }
+            Err(error) => recorded.error = Some(error.clone()),
+        }
+        return recorded;
+    }
+}
+
+/// One finished deployment as it is stored in the history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentRecord {
+    /// RFC 3339 timestamp of when the deployment finished
+    pub finished_at: String,
+    pub user: String,
+    /// Whether `user` comes from an OIDC login instead of `$USER`
+    #[serde(default)]
+    pub verified: bool,
+    pub dry_run: bool,
+    pub versions: Vec<ReleasedVersion>,
+    /// Labels of the checked deployment options
+    pub options: Vec<String>,
+    pub steps: Vec<StepOutcome>,
+    /// Files, URLs and values the steps produced
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+    /// Markdown release notes of the included merge requests
+    #[serde(default)]
+    pub release_notes: String,
+    /// Empty without `[gitlab]` and in records from before approvals were kept
+    #[serde(default)]
+    pub approvals: Vec<RecordedApproval>,
+}
+
+impl DeploymentRecord {
+    pub fn new(
+        deployment: &Deployment,
+        projects: &[Project],
+        approvals: &[ApprovalSummary],
+    ) -> Self {
+        // Only the projects whose deployment went through
+        let versions = deployment
+            .steps
+            .iter()
+            .filter(|step| step.status == StepStatus::Succeeded)
+            .filter_map(|step| {
+                let StepKind::Pipeline(index) = step.kind else {
+                    return None;
+                };
+                let project = &projects[index];
+                let tag = deployment
+                    .artifacts
+                    .get(&format!("{}_tag", project.config.name));
+                return Some(ReleasedVersion {
+                    project: project.config.title.clone(),
+                    version: project.next_version()?,
+                    tag: tag.map(|tag| tag.to_string()),
+                    step: Some(step.label.clone()),
+                });
+            })
+            .collect();
+        let options = deployment
+            .selected_options
+            .iter()
+            .filter(|option| option.value)
+            .map(|option| option.label.clone())
+            .collect();
+        let steps = deployment
+            .steps
+            .iter()
+            .map(|step| {
+                let outcome = match &step.status {
+                    StepStatus::Succeeded => "succeeded".to_string(),
+                    StepStatus::Failed(error) | StepStatus::TimedOut(error) => {
+                        format!("failed: {}", error)
+                    }
+                    StepStatus::Skipped => "skipped".to_string(),
+                    StepStatus::Pending | StepStatus::Running | StepStatus::Held => {
+                        "not run".to_string()
+                    }
+                };
+                return StepOutcome {
+                    label: step.label.clone(),
+                    outcome,
+                };
+            })
+            .collect();
+        return Self {
+            finished_at: chrono::Local::now().to_rfc3339(),
+            user: match &deployment.identity {
+                Some(identity) => identity.name.clone(),
+                None => std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
+            },
+            verified: deployment.identity.is_some(),
+            dry_run: deployment.dry_run,
+            versions,
+            options,
+            steps,
+            artifacts: deployment.artifacts.iter().cloned().collect(),
+            release_notes: release_notes(projects, &deployment.release_notes),
+            approvals: approvals.iter().map(RecordedApproval::from).collect(),
+        };
+    }
+
+    /// The finish time as shown in the UI.
+    pub fn display_time(&self) -> String {
+        return chrono::DateTime::parse_from_rfc3339(&self.finished_at)
+            .map_or(self.finished_at.clone(), |time| {
+                time.format("%Y-%m-%d %H:%M").to_string()
+            });
+    }
+
+    /// The released versions, e.g. "Sulu v252, Sylius v66".
+    pub fn versions_summary(&self) -> String {
+        if self.versions.is_empty() {
+            return "nothing released".to_string();
+        }
+        return self
+            .versions
+            .iter()
+            .map(|version| format!("{} v{}", version.project, version.version))
+            .collect::<Vec<String>>()
+            .join(", ");
+    }
+
+    /// Whether a real deployment released the version, e.g. "v252" or "252", and the step that
+    /// deployed it succeeded.
+    pub fn released(&self, version: &str) -> bool {
+        let Ok(version) = version.trim_start_matches('v').parse::<u32>() else {
+            return false;
+        };
+        return !self.dry_run
+            && self
+                .versions
+                .iter()
+                .filter(|released| released.version == version)
+                .any(|released| {
+                    // Older records kept every project, the default label finds its step
+                    let label = released
+                        .step
+                        .clone()
+                        .unwrap_or_else(|| format!("Starting {} Pipeline", released.project));
+                    return self
+                        .steps
+                        .iter()
+                        .any(|step| step.label == label && step.outcome == "succeeded");
+                });
+    }
+
+    pub fn has_failed(&self) -> bool {
+        return self
+            .steps
+            .iter()
+            .any(|step| step.outcome.starts_with("failed"));
+    }
+}
+
+/// Past deployments, kept in the configured storage so they survive restarts.
+pub struct History {
+    store: Box<dyn HistoryStore>,
+    /// Oldest first
+    pub records: Vec<DeploymentRecord>,
+}
+
+/// `$XDG_DATA_HOME/deployment-tool/history.json`, falling back to `~/.local/share`.
+pub fn default_path() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    return Some(data_home.join("deployment-tool").join("history.json"));
+}
+
+impl History {
+    pub fn load(mut store: Box<dyn HistoryStore>) -> Result<Self> {
+        let records = store.load()?;
+        return Ok(Self { store, records });
+    }
+
+    /// Adds the deployment, it is kept for this run even when it could not be stored.
+    pub fn record(&mut self, record: DeploymentRecord) -> Result<()> {
+        let stored = self.store.append(&record);
+        self.records.push(record);
+        return stored;
+    }
+
+    /// Where the deployments are stored, for messages.
+    pub fn location(&self) -> String {
+        return self.store.location();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tutorial::demo_projects;
+
+    /// The demo deployment: release notes, mail, Shop v42 and Admin v17, with the given
+    /// outcome of the Admin deployment.
+    fn record(admin: StepStatus) -> DeploymentRecord {
+        let projects = demo_projects();
+        let mut deployment = Deployment::new(&projects);
+        let statuses = [
+            StepStatus::Succeeded,
+            StepStatus::Skipped,
+            StepStatus::Succeeded,
+            admin,
+        ];
+        for (step, status) in deployment.steps.iter_mut().zip(statuses) {
+            step.status = status;
+        }
+        return DeploymentRecord::new(&deployment, &projects, &[]);
+    }
+
+    #[test]
+    fn a_failed_deployment_did_not_release_its_version() {
+        let record = record(StepStatus::Failed("exit code 1".to_string()));
+        assert!(record.released("v42"));
+        assert!(!record.released("v17"));
+        assert_eq!(record.versions_summary(), "Shop v42");
+    }
+
+    #[test]
+    fn an_unselected_project_did_not_release_its_version() {
+        let record = record(StepStatus::Skipped);
+        assert!(record.released("42"));
+        assert!(!record.released("17"));
+    }
+
+    #[test]
+    fn a_dry_run_released_nothing() {
+        let mut record = record(StepStatus::Succeeded);
+        assert!(record.released("v17"));
+        record.dry_run = true;
+        assert!(!record.released("v17"));
+    }
+
+    #[test]
+    fn older_records_check_the_deployment_step_too() {
+        let record: DeploymentRecord = serde_json::from_value(serde_json::json!({
+            "finished_at": "2026-03-02T10:00:00+01:00",
+            "user": "anna",
+            "dry_run": false,
+            "versions": [
+                { "project": "Shop", "version": 42 },
+                { "project": "Admin", "version": 17 },
+            ],
+            "options": [],
+            "steps": [
+                { "label": "Starting Shop Pipeline", "outcome": "succeeded" },
+                { "label": "Starting Admin Pipeline", "outcome": "failed: exit code 1" },
+            ],
+        }))
+        .unwrap();
+        assert!(record.released("v42"));
+        assert!(!record.released("v17"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::OidcConfig;
+use crate::session;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Who runs the tool, as the identity provider knows them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    /// Stable id of the user at the issuer
+    pub subject: String,
+    /// Username, email or, as a last resort, the subject
+    pub name: String,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    device_authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_interval() -> u64 {
+    return 5;
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    expires_in: Option<i64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfo {
+    sub: String,
+    preferred_username: Option<String>,
+    email: Option<String>,
+}
+
+/// The access token of the last login, so not every start needs one.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedToken {
+    issuer: String,
+    access_token: String,
+    /// RFC 3339
+    expires_at: String,
+}
+
+/// `identity.json` next to the session file.
+fn cache_path() -> Option<PathBuf> {
+    return Some(session::default_path()?.parent()?.join("identity.json"));
+}
+
+/// Logs in with the OAuth device flow: prints a URL and a code to enter there, then waits until
+/// that happened. The identity is what the issuer's userinfo endpoint tells for the token, the
+/// token is kept until it expires.
+pub fn login(config: &OidcConfig) -> Result<Identity> {
+    let issuer = config.issuer.trim_end_matches('/');
+    let agent = ureq::AgentBuilder::new().build();
+    let discovery_url = format!("{}/.well-known/openid-configuration", issuer);
+    let discovery: Discovery = agent
+        .get(&discovery_url)
+        .call()
+        .wrap_err_with(|| format!("Could not reach the identity provider at {}", issuer))?
+        .into_json()
+        .wrap_err_with(|| format!("Unexpected response from {}", discovery_url))?;
+
+    if let Some(cached) = load_cached(issuer) {
+        if let Ok(identity) = user_info(&agent, &discovery, &cached.access_token) {
+            return Ok(identity);
+        }
+    }
+
+    let authorization: DeviceAuthorization = agent
+        .post(&discovery.device_authorization_endpoint)
+        .send_form(&[
+            ("client_id", config.client_id.as_str()),
+            ("scope", config.scope.as_str()),
+        ])
+        .wrap_err("Could not start the login")?
+        .into_json()
+        .wrap_err("Unexpected response when starting the login")?;
+    match &authorization.verification_uri_complete {
+        Some(uri) => eprintln!("Log in at {}", uri),
+        None => eprintln!(
+            "Log in at {} with the code {}",
+            authorization.verification_uri, authorization.user_code
+        ),
+    }
+
+    let mut interval = Duration::from_secs(authorization.interval);
+    let token = loop {
+        std::thread::sleep(interval);
+        // Pending logins are answered with an error status, the body tells why
+        let response = match agent.post(&discovery.token_endpoint).send_form(&[
+            ("grant_type", DEVICE_CODE_GRANT),
+            ("device_code", authorization.device_code.as_str()),
+            ("client_id", config.client_id.as_str()),
+        ]) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(error) => return Err(error).wrap_err("Could not finish the login"),
+        };
+        let token: TokenResponse = response
+            .into_json()
+            .wrap_err("Unexpected response when finishing the login")?;
+        match token.error.as_deref() {
+            None => break token,
+            Some("authorization_pending") => {}
+            Some("slow_down") => interval += Duration::from_secs(5),
+            Some(error) => bail!(
+                "Login failed: {}",
+                token.error_description.as_deref().unwrap_or(error)
+            ),
+        }
+    };
+    let Some(access_token) = token.access_token else {
+        bail!("The identity provider sent no access token");
+    };
+    let identity = user_info(&agent, &discovery, &access_token)?;
+    // Without an expiry the token is only used for this run
+    if let Some(expires_in) = token.expires_in {
+        let expires_at = chrono::Local::now() + chrono::Duration::seconds(expires_in);
+        save_cached(&CachedToken {
+            issuer: issuer.to_string(),
+            access_token,
+            expires_at: expires_at.to_rfc3339(),
+        })?;
+    }
+    return Ok(identity);
+}
+
+fn user_info(agent: &ureq::Agent, discovery: &Discovery, access_token: &str) -> Result<Identity> {
+    let info: UserInfo = agent
+        .get(&discovery.userinfo_endpoint)
+        .set("Authorization", &format!("Bearer {}", access_token))
+        .call()
+        .map_err(|error| eyre!("Could not look up who logged in: {}", error))?
+        .into_json()
+        .wrap_err("Unexpected user info")?;
+    let name = info
+        .preferred_username
+        .clone()
+        .or(info.email.clone())
+        .unwrap_or(info.sub.clone());
+    return Ok(Identity {
+        subject: info.sub,
+        name,
+        email: info.email,
+    });
+}
+
+/// The cached token of the issuer, if it has not expired yet.
+fn load_cached(issuer: &str) -> Option<CachedToken> {
+    let content = std::fs::read_to_string(cache_path()?).ok()?;
+    let cached: CachedToken = serde_json::from_str(&content).ok()?;
+    let expires_at = chrono::DateTime::parse_from_rfc3339(&cached.expires_at).ok()?;
+    if cached.issuer != issuer || expires_at < chrono::Local::now() {
+        return None;
+    }
+    return Some(cached);
+}
+
+fn save_cached(token: &CachedToken) -> Result<()> {
+    let Some(path) = cache_path() else {
+        return Ok(());
+    };
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)
+            .wrap_err_with(|| format!("Could not create {}", directory.display()))?;
+    }
+    std::fs::write(&path, serde_json::to_string(token)?)
+        .wrap_err_with(|| format!("Could not write {}", path.display()))?;
+    // Anyone reading it could act as the user
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .wrap_err_with(|| format!("Could not restrict {}", path.display()))?;
+    }
+    return Ok(());
+}
@@ -0,0 +1,64 @@
+use std::sync::mpsc::{self, Receiver};
+
+use crate::gitlab::{GitlabClient, Todo};
+
+/// The user's GitLab to-dos for the tracked projects, loaded in the background.
+#[derive(Default)]
+pub struct Inbox {
+    /// None until the first load finished
+    pub todos: Option<Result<Vec<Todo>, String>>,
+    /// Index into `todos`
+    pub selected: usize,
+    receiver: Option<Receiver<Result<Vec<Todo>, String>>>,
+}
+
+impl Inbox {
+    /// Loads the to-dos again, keeping only those of the given projects.
+    pub fn refresh(&mut self, gitlab: &GitlabClient, project_ids: Vec<u64>) {
+        if self.is_loading() {
+            return;
+        }
+        let gitlab = gitlab.clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let todos = gitlab
+                .todos()
+                .map(|todos| {
+                    return todos
+                        .into_iter()
+                        .filter(|todo| {
+                            todo.project
+                                .as_ref()
+                                .is_some_and(|project| project_ids.contains(&project.id))
+                        })
+                        .collect();
+                })
+                .map_err(|error| format!("{:#}", error));
+            let _ = sender.send(todos);
+        });
+        self.receiver = Some(receiver);
+    }
+
+    /// Picks up the to-dos once they are loaded, call this before rendering.
+    pub fn update(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        if let Ok(todos) = receiver.try_recv() {
+            self.todos = Some(todos);
+            self.selected = self.selected.min(self.count().saturating_sub(1));
+            self.receiver = None;
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        return self.receiver.is_some();
+    }
+
+    pub fn count(&self) -> usize {
+        return match &self.todos {
+            Some(Ok(todos)) => todos.len(),
+            _ => 0,
+        };
+    }
+}
@@ -0,0 +1,2272 @@
+#![allow(clippy::needless_return)]
+
+pub mod analytics;
+pub mod approvals;
+pub mod artifacts;
+pub mod audit;
+pub mod browser;
+pub mod cli;
+pub mod config;
+pub mod deployment;
+pub mod fetcher;
+pub mod gitlab;
+pub mod headless;
+pub mod history;
+pub mod identity;
+pub mod inbox;
+pub mod lint;
+pub mod macros;
+pub mod mail;
+pub mod notifications;
+pub mod palette;
+pub mod release_notes;
+pub mod session;
+pub mod static_site;
+pub mod storage;
+pub mod telemetry;
+pub mod tutorial;
+pub mod undo;
+pub mod watch;
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+use analytics::ReleaseComposition;
+use approvals::Approvals;
+use color_eyre::Result;
+use config::{ColumnConfig, Config, ProjectConfig};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use deployment::{Deployment, FailureDecision, HoldDecision, StepStatus};
+use fetcher::ChangelogFetcher;
+use gitlab::GitlabClient;
+use history::{DeploymentRecord, History};
+use inbox::Inbox;
+use macros::{MacroPrompt, Macros};
+use palette::{CommandPalette, PaletteAction, PaletteEntry};
+use ratatui::{
+    layout::{Constraint, Direction, Flex, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Table, TableState, Tabs, Wrap,
+    },
+    DefaultTerminal, Frame,
+};
+use session::{Session, View};
+use telemetry::Usage;
+use tutorial::Tutorial;
+use undo::{UiChange, UndoStack};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurrentCommit {
+    commit_hash: String,
+    title: String,
+    author_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Changelog {
+    next_version_number: u32,
+    commit: CurrentCommit,
+    current_time: String,
+    merge_requests: Vec<MergeRequest>,
+}
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeRequest {
+    ticket_number: String,
+    title: String,
+    github: String,
+    /// Labels, given as one comma separated string in the changelog
+    #[serde(deserialize_with = "deserialize_flags")]
+    flags: Vec<String>,
+    #[serde(default)]
+    description: String,
+    /// Values of the extra columns by title, filled in after fetching
+    #[serde(skip)]
+    columns: BTreeMap<String, String>,
+}
+
+fn deserialize_flags<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let flags = String::deserialize(deserializer)?;
+    return Ok(flags
+        .split(',')
+        .map(|flag| flag.trim())
+        .filter(|flag| !flag.is_empty())
+        .map(|flag| flag.to_string())
+        .collect());
+}
+
+/// Flags that need attention when deploying: breaking changes and migrations.
+pub fn is_breaking_flag(flag: &str) -> bool {
+    let flag = flag.to_lowercase();
+    return flag.contains("breaking") || flag.contains("migration");
+}
+
+#[derive(Debug)]
+pub enum ChangelogError {
+    /// The changelog command could not be started
+    Command(std::io::Error),
+    /// The changelog command exited unsuccessfully
+    Failed {
+        status: ExitStatus,
+        stderr: String,
+    },
+    InvalidJson(serde_json::Error),
+    Gitlab(color_eyre::Report),
+}
+
+impl fmt::Display for ChangelogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangelogError::Command(error) => {
+                write!(f, "Could not run the changelog command: {}", error)
+            }
+            ChangelogError::Failed { status, stderr } => {
+                write!(
+                    f,
+                    "Changelog command failed ({}): {}",
+                    status,
+                    stderr.trim()
+                )
+            }
+            ChangelogError::InvalidJson(error) => {
+                write!(f, "Changelog JSON was not well-formatted: {}", error)
+            }
+            ChangelogError::Gitlab(error) => {
+                let causes: Vec<String> = error.chain().map(|cause| cause.to_string()).collect();
+                write!(f, "{}", causes.join(": "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChangelogError {}
+
+pub fn get_changelog_info(
+    config: &Config,
+    project: &ProjectConfig,
+    token: &str,
+) -> std::result::Result<Changelog, ChangelogError> {
+    if let Some(gitlab) = config.gitlab.as_ref().filter(|gitlab| gitlab.changelog) {
+        return GitlabClient::new(&gitlab.url, token)
+            .changelog(project.project_id)
+            .map_err(ChangelogError::Gitlab);
+    }
+
+    let command = &config.changelog;
+    let output = std::process::Command::new(&command.command)
+        .args(command.args_for(project, token))
+        .output()
+        .map_err(ChangelogError::Command)?;
+    if !output.status.success() {
+        return Err(ChangelogError::Failed {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    let output_content = String::from_utf8_lossy(&output.stdout);
+    return serde_json::from_str(&output_content).map_err(ChangelogError::InvalidJson);
+}
+
+impl Changelog {
+    /// Looks up the values of the extra columns the project shows.
+    pub fn fill_columns(&mut self, columns: &[ColumnConfig], project: &ProjectConfig) {
+        for column in visible_columns(columns, project) {
+            for merge_request in self.merge_requests.iter_mut() {
+                if let Some(value) = column.value(merge_request, project) {
+                    merge_request.columns.insert(column.title.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+/// The extra columns a project shows, with its own widths applied.
+pub fn visible_columns(columns: &[ColumnConfig], project: &ProjectConfig) -> Vec<ColumnConfig> {
+    return columns
+        .iter()
+        .filter_map(|column| {
+            let Some(config) = project.columns.get(&column.title) else {
+                return Some(column.clone());
+            };
+            if !config.visible {
+                return None;
+            }
+            let mut column = column.clone();
+            column.width = config.width.unwrap_or(column.width);
+            return Some(column);
+        })
+        .collect();
+}
+
+pub fn print_changelog(projects: &[Project]) {
+    for project in projects.iter() {
+        let Some(changelog) = &project.changelog else {
+            continue;
+        };
+        println!(
+            "{}: Version {} ({})",
+            project.config.title, changelog.next_version_number, changelog.current_time
+        );
+        println!(
+            "Commit: {}({})",
+            changelog.commit.title, changelog.commit.commit_hash
+        );
+        println!("Author: {}", changelog.commit.author_name);
+        println!("{}", project.composition.summary());
+        for merge_request in changelog.merge_requests.iter() {
+            println!(
+                "  {:<8} {} {} {}",
+                merge_request.ticket_number,
+                merge_request.title,
+                merge_request.github,
+                merge_request.flags.join(", ")
+            );
+        }
+        println!();
+    }
+}
+
+/// A configured project together with the changelog fetched for it.
+#[derive(Clone)]
+pub struct Project {
+    pub config: ProjectConfig,
+    /// `None` while the changelog is still being fetched
+    pub changelog: Option<Changelog>,
+    /// Why fetching the changelog failed, shown instead of the changelog
+    pub error: Option<String>,
+    /// A newer changelog is being fetched, the current one stays shown until it arrives
+    pub refreshing: bool,
+    /// A refresh brought new merge requests or commits, until the project is selected
+    pub changed: bool,
+    /// Merge requests pulled from the release, indices into `Changelog.merge_requests`
+    excluded: BTreeSet<usize>,
+    composition: ReleaseComposition,
+}
+
+impl Project {
+    pub fn new(config: ProjectConfig, changelog: Changelog) -> Self {
+        let mut project = Self::loading(config);
+        project.set_changelog(changelog);
+        return project;
+    }
+
+    pub fn loading(config: ProjectConfig) -> Self {
+        return Self {
+            config,
+            changelog: None,
+            error: None,
+            refreshing: false,
+            changed: false,
+            excluded: BTreeSet::new(),
+            composition: ReleaseComposition::default(),
+        };
+    }
+
+    /// Shows a fetched changelog, merge requests that were excluded before stay excluded.
+    pub fn set_changelog(&mut self, changelog: Changelog) {
+        let excluded: HashSet<&str> = self
+            .changelog
+            .iter()
+            .flat_map(|old| old.merge_requests.iter().enumerate())
+            .filter(|(row, _)| self.excluded.contains(row))
+            .map(|(_, merge_request)| merge_request.github.as_str())
+            .collect();
+        if let Some(old) = &self.changelog {
+            self.changed |= old.commit.commit_hash != changelog.commit.commit_hash
+                || old.merge_requests.len() != changelog.merge_requests.len()
+                || old
+                    .merge_requests
+                    .iter()
+                    .zip(changelog.merge_requests.iter())
+                    .any(|(old, new)| old.github != new.github);
+        }
+        self.excluded = changelog
+            .merge_requests
+            .iter()
+            .enumerate()
+            .filter(|(_, merge_request)| excluded.contains(merge_request.github.as_str()))
+            .map(|(row, _)| row)
+            .collect();
+        self.changelog = Some(changelog);
+        self.composition = ReleaseComposition::from_merge_requests(self.included_merge_requests());
+        self.refreshing = false;
+        self.error = None;
+    }
+
+    pub fn is_excluded(&self, row: usize) -> bool {
+        return self.excluded.contains(&row);
+    }
+
+    /// Pulls a merge request from the release, or puts it back.
+    pub fn toggle_excluded(&mut self, row: usize) {
+        if !self.excluded.remove(&row) {
+            self.excluded.insert(row);
+        }
+        self.composition = ReleaseComposition::from_merge_requests(self.included_merge_requests());
+    }
+
+    /// The merge requests that are part of the release.
+    pub fn included_merge_requests(&self) -> impl Iterator<Item = &MergeRequest> {
+        return self
+            .changelog
+            .iter()
+            .flat_map(|changelog| changelog.merge_requests.iter().enumerate())
+            .filter(|(row, _)| !self.excluded.contains(row))
+            .map(|(_, merge_request)| merge_request);
+    }
+
+    /// The version this release gets, `None` while loading or when every merge request is
+    /// excluded and there is nothing to release.
+    pub fn next_version(&self) -> Option<u32> {
+        let changelog = self.changelog.as_ref()?;
+        if self.included_merge_requests().next().is_none() && !changelog.merge_requests.is_empty() {
+            return None;
+        }
+        return Some(changelog.next_version_number);
+    }
+
+    /// The commit the changelog was made at, which is what gets deployed.
+    pub fn commit_hash(&self) -> Option<&str> {
+        return Some(&self.changelog.as_ref()?.commit.commit_hash);
+    }
+
+    pub fn set_error(&mut self, error: &ChangelogError) {
+        self.changelog = None;
+        self.refreshing = false;
+        self.error = Some(error.to_string());
+    }
+}
+
+/// Something that has to be confirmed with y first.
+enum Confirmation {
+    /// Checking an option, index into `Deployment.selected_options`
+    CheckOption(usize),
+    /// Undoing or redoing a change that checks the option with this index
+    Undo(usize),
+    Redo(usize),
+    CancelDeployment,
+    /// Quitting while a deployment runs cancels it first
+    Quit,
+}
+
+pub struct App {
+    /// Index into `projects`
+    pub selected: usize,
+    /// Selected and first visible merge request row of the selected project
+    pub merge_requests: TableState,
+    pub ready_for_deployment: bool,
+    pub deployment: Deployment,
+    pub projects: Vec<Project>,
+    pub gitlab: Option<GitlabClient>,
+    /// Finished deployments are recorded here when set
+    pub history: Option<History>,
+    pub show_history: bool,
+    /// Index into `history.records`, counted from the newest
+    history_selected: usize,
+    /// Why the last deployment could not be recorded
+    history_error: Option<String>,
+    /// Ticket link template from the config, `{ticket}` is replaced
+    pub ticket_url: Option<String>,
+    /// GitLab to-dos for the tracked projects, loaded when the inbox is opened
+    pub inbox: Inbox,
+    pub show_inbox: bool,
+    /// Review evidence of the included merge requests, loaded when the screen is opened
+    pub approvals: Approvals,
+    pub show_approvals: bool,
+    /// Open command palette, shown on top of the current view
+    pub palette: Option<CommandPalette>,
+    /// Projects shown side by side in compare mode, as indices into `projects`
+    pub compare: Option<(usize, usize)>,
+    /// First merge request row shown in both compared tables
+    compare_scroll: usize,
+    undo: UndoStack,
+    /// Waiting for y/n before it happens
+    confirmation: Option<Confirmation>,
+    /// Quit once the cancelled deployment stopped and is recorded
+    quitting: bool,
+    /// Since when the finished deployment waits for its approvals to be recorded with them
+    recording: Option<Instant>,
+    /// Whether the out of turn warning was shown, the next start goes ahead
+    out_of_turn_warned: bool,
+    pub macros: Macros,
+    /// Short message shown in the bottom right corner until the next key press
+    status: Option<String>,
+    fetcher: Option<ChangelogFetcher>,
+    /// Number of redraws, drives the loading spinners
+    tick: usize,
+    /// Refetch all changelogs this often while the overview is shown
+    pub auto_refresh: Option<Duration>,
+    last_refresh: Instant,
+    /// Guides through a dry run release of the demo projects when set
+    pub tutorial: Option<Tutorial>,
+    /// Extra columns of the merge request table from the config
+    pub columns: Vec<ColumnConfig>,
+    /// Screens and steps used, for the telemetry
+    pub usage: Usage,
+}
+
+impl App {
+    pub fn new(
+        projects: Vec<Project>,
+        gitlab: Option<GitlabClient>,
+        fetcher: Option<ChangelogFetcher>,
+    ) -> Self {
+        return Self {
+            selected: 0,
+            merge_requests: TableState::default(),
+            ready_for_deployment: false,
+            deployment: Deployment::new(&projects),
+            projects,
+            gitlab,
+            history: None,
+            show_history: false,
+            history_selected: 0,
+            history_error: None,
+            ticket_url: None,
+            inbox: Inbox::default(),
+            show_inbox: false,
+            approvals: Approvals::default(),
+            show_approvals: false,
+            palette: None,
+            compare: None,
+            compare_scroll: 0,
+            undo: UndoStack::default(),
+            confirmation: None,
+            quitting: false,
+            recording: None,
+            out_of_turn_warned: false,
+            macros: Macros::default(),
+            status: None,
+            fetcher,
+            tick: 0,
+            auto_refresh: None,
+            last_refresh: Instant::now(),
+            tutorial: None,
+            columns: vec![],
+            usage: Usage::default(),
+        };
+    }
+
+    pub fn get_current_commit_status(&self) -> Option<&Changelog> {
+        return self.projects[self.selected].changelog.as_ref();
+    }
+
+    pub fn get_current_composition(&self) -> &ReleaseComposition {
+        return &self.projects[self.selected].composition;
+    }
+
+    pub fn all_changelogs_loaded(&self) -> bool {
+        return self
+            .projects
+            .iter()
+            .all(|project| project.changelog.is_some());
+    }
+
+    /// Applies changelogs fetched in the background and progress of a running deployment.
+    pub fn update(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+        if let Some(fetcher) = &self.fetcher {
+            for update in fetcher.received() {
+                let project = &mut self.projects[update.project];
+                match update.changelog {
+                    Ok(changelog) => project.set_changelog(changelog),
+                    // A failed refresh keeps the changelog that was already loaded
+                    Err(error) if project.refreshing && project.changelog.is_some() => {
+                        self.usage.error("changelog");
+                        project.refreshing = false;
+                        self.status = Some(format!(
+                            "Could not refresh {}: {}",
+                            project.config.title, error
+                        ));
+                    }
+                    Err(error) => {
+                        self.usage.error("changelog");
+                        project.set_error(&error);
+                    }
+                }
+            }
+        }
+        let overview_shown = !self.ready_for_deployment
+            && !self.show_history
+            && !self.show_inbox
+            && !self.show_approvals
+            && self.compare.is_none();
+        if let Some(interval) = self.auto_refresh {
+            if overview_shown && self.last_refresh.elapsed() >= interval {
+                self.last_refresh = Instant::now();
+                self.refresh_all();
+            }
+        }
+        self.inbox.update();
+        self.approvals.update();
+        let was_running = self.deployment.deployment_running;
+        self.deployment.update();
+        if was_running && !self.deployment.deployment_running {
+            self.usage.deployment(&self.deployment);
+            if self.history.is_some() {
+                self.recording = Some(Instant::now());
+            }
+        }
+        // Loading the approvals started with the deployment
+        if let Some(since) = self.recording {
+            if !self.approvals.is_loading() || since.elapsed() > APPROVALS_TIMEOUT {
+                self.recording = None;
+                self.record_deployment();
+            }
+        }
+        let step_done = self
+            .tutorial
+            .as_ref()
+            .is_some_and(|tutorial| tutorial.step().is_done(self));
+        if let Some(tutorial) = self.tutorial.as_mut().filter(|_| step_done) {
+            tutorial.next();
+        }
+    }
+
+    fn record_deployment(&mut self) {
+        let Some(history) = &mut self.history else {
+            return;
+        };
+        // Still loading after the timeout, older ones could be of other merge requests
+        let approvals = if self.approvals.is_loading() {
+            &[]
+        } else {
+            self.approvals.summaries.as_deref().unwrap_or_default()
+        };
+        let record = DeploymentRecord::new(&self.deployment, &self.projects, approvals);
+        self.history_error = history.record(record).err().map(|error| error.to_string());
+    }
+
+    /// Everything the command palette can search: actions first, then merge requests and releases.
+    pub fn palette_entries(&self) -> Vec<PaletteEntry> {
+        let mut entries = vec![
+            PaletteEntry {
+                label: "Start deployment".to_string(),
+                action: PaletteAction::StartDeployment,
+            },
+            PaletteEntry {
+                label: "Show changelogs".to_string(),
+                action: PaletteAction::ShowOverview,
+            },
+            PaletteEntry {
+                label: "Show deployment options".to_string(),
+                action: PaletteAction::ShowDeployment,
+            },
+            PaletteEntry {
+                label: "Open deployment history".to_string(),
+                action: PaletteAction::ShowHistory,
+            },
+            PaletteEntry {
+                label: "Open GitLab to-dos".to_string(),
+                action: PaletteAction::ShowInbox,
+            },
+            PaletteEntry {
+                label: "Show approvals".to_string(),
+                action: PaletteAction::ShowApprovals,
+            },
+        ];
+        entries.push(PaletteEntry {
+            label: "Toggle dry run".to_string(),
+            action: PaletteAction::ToggleDryRun,
+        });
+        for (index, option) in self.deployment.selected_options.iter().enumerate() {
+            entries.push(PaletteEntry {
+                label: format!("Toggle option: {}", option.label),
+                action: PaletteAction::ToggleOption(index),
+            });
+        }
+        for (index, project) in self.projects.iter().enumerate() {
+            entries.push(PaletteEntry {
+                label: format!("Switch to project: {}", project.config.title),
+                action: PaletteAction::SelectProject(index),
+            });
+        }
+        for (index, project) in self.projects.iter().enumerate() {
+            let Some(changelog) = &project.changelog else {
+                continue;
+            };
+            for (row, merge_request) in changelog.merge_requests.iter().enumerate() {
+                entries.push(PaletteEntry {
+                    label: format!(
+                        "{} {} ({})",
+                        merge_request.ticket_number, merge_request.title, project.config.title
+                    ),
+                    action: PaletteAction::SelectMergeRequest(index, row),
+                });
+            }
+        }
+        if let Some(history) = &self.history {
+            for (index, record) in history.records.iter().rev().enumerate() {
+                entries.push(PaletteEntry {
+                    label: format!(
+                        "Release {} {} by {}",
+                        record.display_time(),
+                        record.versions_summary(),
+                        record.user
+                    ),
+                    action: PaletteAction::SelectRelease(index),
+                });
+            }
+        }
+        return entries;
+    }
+
+    /// Handles a key press while the command palette is open.
+    fn palette_key(&mut self, code: KeyCode) {
+        let entries = self.palette_entries();
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        let matches = palette.matches(&entries);
+        match code {
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Backspace => palette.pop(),
+            KeyCode::Up if palette.selected > 0 => palette.selected -= 1,
+            KeyCode::Down if palette.selected + 1 < matches.len() => palette.selected += 1,
+            KeyCode::Char(character) => palette.push(character),
+            KeyCode::Enter => {
+                let Some(entry) = matches.get(palette.selected) else {
+                    return;
+                };
+                let action = entry.action.clone();
+                self.palette = None;
+                self.apply(action);
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts the deployment, unless someone else is release manager and this is the first
+    /// attempt: then only the warning is shown.
+    fn start_deployment(&mut self) {
+        // The record would get the steps of the new deployment
+        if self.recording.is_some() {
+            self.status =
+                Some("Recording the last deployment, start again in a moment".to_string());
+            return;
+        }
+        if !self.out_of_turn_warned {
+            if let Some(warning) = self.deployment.out_of_turn_warning() {
+                self.status = Some(format!("{}, start again to deploy anyway", warning));
+                self.out_of_turn_warned = true;
+                return;
+            }
+        }
+        self.deployment.start(&self.projects, self.gitlab.as_ref());
+        // Kept in the history as review evidence
+        self.refresh_approvals();
+    }
+
+    fn apply(&mut self, action: PaletteAction) {
+        self.show_history = false;
+        self.show_inbox = false;
+        self.show_approvals = false;
+        match action {
+            PaletteAction::StartDeployment => {
+                self.ready_for_deployment = true;
+                if self.all_changelogs_loaded() {
+                    self.start_deployment();
+                }
+            }
+            PaletteAction::ShowOverview => self.ready_for_deployment = false,
+            PaletteAction::ShowDeployment => self.ready_for_deployment = true,
+            PaletteAction::ShowHistory => {
+                self.show_history = true;
+                self.history_selected = 0;
+            }
+            PaletteAction::ShowInbox => self.open_inbox(),
+            PaletteAction::ShowApprovals => self.open_approvals(),
+            PaletteAction::ToggleOption(index) => self.toggle_option(index),
+            PaletteAction::ToggleDryRun => self.change(UiChange::ToggleDryRun),
+            PaletteAction::SelectProject(index) => {
+                self.ready_for_deployment = false;
+                self.select_project(index);
+            }
+            PaletteAction::SelectMergeRequest(index, row) => {
+                self.ready_for_deployment = false;
+                self.select_project(index);
+                self.merge_requests.select(Some(row));
+            }
+            PaletteAction::SelectRelease(index) => {
+                self.show_history = true;
+                self.history_selected = index;
+            }
+        }
+    }
+
+    /// Toggles a deployment option, options that need confirmation ask first when checked.
+    pub fn toggle_option(&mut self, index: usize) {
+        let option = &self.deployment.selected_options[index];
+        if option.confirm && !option.value && !self.deployment.deployment_running {
+            self.ready_for_deployment = true;
+            self.deployment.current_option = index;
+            self.confirmation = Some(Confirmation::CheckOption(index));
+            return;
+        }
+        self.change(UiChange::ToggleOption(index));
+    }
+
+    /// Makes a change that Ctrl+Z can revert.
+    pub fn change(&mut self, change: UiChange) {
+        if self.apply_change(&change) {
+            self.undo.push(change);
+        }
+    }
+
+    /// Reverts the last change, checking an option that needs confirmation asks first.
+    pub fn undo(&mut self) {
+        if let Some(index) = self.checks_confirmed_option(self.undo.next_undo().cloned()) {
+            self.confirmation = Some(Confirmation::Undo(index));
+            return;
+        }
+        self.apply_undo();
+    }
+
+    /// Applies the last reverted change again, checking an option that needs confirmation asks
+    /// first.
+    pub fn redo(&mut self) {
+        if let Some(index) = self.checks_confirmed_option(self.undo.next_redo().cloned()) {
+            self.confirmation = Some(Confirmation::Redo(index));
+            return;
+        }
+        self.apply_redo();
+    }
+
+    fn apply_undo(&mut self) {
+        if self.deployment.deployment_running {
+            return;
+        }
+        if let Some(change) = self.undo.undo() {
+            self.apply_change(&change);
+        }
+    }
+
+    fn apply_redo(&mut self) {
+        if self.deployment.deployment_running {
+            return;
+        }
+        if let Some(change) = self.undo.redo() {
+            self.apply_change(&change);
+        }
+    }
+
+    /// The option the change would check when checking it has to be confirmed, after showing it.
+    fn checks_confirmed_option(&mut self, change: Option<UiChange>) -> Option<usize> {
+        let Some(UiChange::ToggleOption(index)) = change else {
+            return None;
+        };
+        let option = &self.deployment.selected_options[index];
+        if !option.confirm || option.value || self.deployment.deployment_running {
+            return None;
+        }
+        self.ready_for_deployment = true;
+        self.deployment.current_option = index;
+        return Some(index);
+    }
+
+    /// Applies (or reverts, changes are their own inverse) a change and shows where it happened.
+    /// Nothing can change while a deployment runs.
+    fn apply_change(&mut self, change: &UiChange) -> bool {
+        if self.deployment.deployment_running {
+            return false;
+        }
+        match change {
+            UiChange::ToggleOption(index) => {
+                self.show_history = false;
+                self.ready_for_deployment = true;
+                self.deployment.current_option = *index;
+                let option = &mut self.deployment.selected_options[*index];
+                option.value = !option.value;
+            }
+            UiChange::ToggleDryRun if self.tutorial.is_some() => {
+                self.status = Some("The tutorial only does dry runs".to_string());
+                return false;
+            }
+            UiChange::ToggleDryRun => {
+                self.show_history = false;
+                self.ready_for_deployment = true;
+                self.deployment.dry_run = !self.deployment.dry_run;
+            }
+            UiChange::ExcludeMergeRequest { project, row } => {
+                // The changelog may have been reloaded since
+                let count = self.projects[*project]
+                    .changelog
+                    .as_ref()
+                    .map_or(0, |changelog| changelog.merge_requests.len());
+                if *row >= count {
+                    return false;
+                }
+                self.show_history = false;
+                self.ready_for_deployment = false;
+                self.select_project(*project);
+                self.merge_requests.select(Some(*row));
+                self.projects[*project].toggle_excluded(*row);
+            }
+        }
+        return true;
+    }
+
+    /// The UI state to save when quitting.
+    pub fn session(&self) -> Session {
+        let view = if self.show_history {
+            View::History
+        } else if self.ready_for_deployment {
+            View::Deployment
+        } else {
+            View::Overview
+        };
+        let options = &self.deployment.selected_options;
+        return Session {
+            view,
+            project: self
+                .projects
+                .get(self.selected)
+                .map(|project| project.config.name.clone()),
+            current_option: options
+                .get(self.deployment.current_option)
+                .map(|option| option.label.clone()),
+            options: options
+                .iter()
+                .map(|option| (option.label.clone(), option.value))
+                .collect(),
+            history_selected: self.history_selected,
+            merge_request: self.merge_requests.selected(),
+            merge_request_offset: self.merge_requests.offset(),
+            log_scroll: self.deployment.log_scroll,
+        };
+    }
+
+    /// Restores a saved session, parts that no longer match the config are ignored. Options
+    /// that have to be confirmed stay unchecked, checking them again asks again.
+    pub fn restore(&mut self, session: &Session) {
+        self.show_history = session.view == View::History;
+        self.ready_for_deployment = session.view == View::Deployment;
+        self.history_selected = session
+            .history_selected
+            .min(self.history_len().saturating_sub(1));
+        if let Some(index) = self
+            .projects
+            .iter()
+            .position(|project| Some(&project.config.name) == session.project.as_ref())
+        {
+            self.selected = index;
+        }
+        // Rows past the end of a shorter changelog are clamped when rendering
+        self.merge_requests = TableState::default()
+            .with_selected(session.merge_request)
+            .with_offset(session.merge_request_offset);
+        self.deployment.log_scroll = session.log_scroll;
+        let options = &mut self.deployment.selected_options;
+        for (label, value) in session.options.iter() {
+            if let Some(option) = options.iter_mut().find(|option| &option.label == label) {
+                if !(*value && option.confirm) {
+                    option.value = *value;
+                }
+            }
+        }
+        if let Some(index) = options
+            .iter()
+            .position(|option| Some(&option.label) == session.current_option.as_ref())
+        {
+            self.deployment.current_option = index;
+        }
+    }
+
+    /// Shows the inbox and loads the to-dos again.
+    pub fn open_inbox(&mut self) {
+        self.show_inbox = true;
+        self.refresh_inbox();
+    }
+
+    fn refresh_inbox(&mut self) {
+        let Some(gitlab) = &self.gitlab else {
+            return;
+        };
+        let project_ids = self
+            .projects
+            .iter()
+            .map(|project| project.config.project_id)
+            .collect();
+        self.inbox.refresh(gitlab, project_ids);
+    }
+
+    /// Shows the approvals of the included merge requests and loads them again.
+    pub fn open_approvals(&mut self) {
+        self.show_approvals = true;
+        self.refresh_approvals();
+    }
+
+    fn refresh_approvals(&mut self) {
+        let Some(gitlab) = &self.gitlab else {
+            return;
+        };
+        self.approvals
+            .refresh(gitlab, approvals::included(&self.projects));
+    }
+
+    fn selected_merge_request(&self) -> Option<&MergeRequest> {
+        let changelog = self.get_current_commit_status()?;
+        return changelog
+            .merge_requests
+            .get(self.merge_requests.selected()?);
+    }
+
+    /// Pulls the selected merge request from the release, or puts it back.
+    fn exclude_selected(&mut self) {
+        let Some(row) = self.merge_requests.selected() else {
+            self.status = Some("Select a merge request with Up/Down first".to_string());
+            return;
+        };
+        if self.selected_merge_request().is_none() {
+            return;
+        }
+        self.change(UiChange::ExcludeMergeRequest {
+            project: self.selected,
+            row,
+        });
+    }
+
+    /// Opens the selected merge request, or its ticket, in the browser.
+    fn open_merge_request(&mut self, ticket: bool) {
+        let Some(merge_request) = self.selected_merge_request() else {
+            self.status = Some("Select a merge request with Up/Down first".to_string());
+            return;
+        };
+        let url = if ticket {
+            let Some(template) = &self.ticket_url else {
+                self.status = Some("No ticket_url configured".to_string());
+                return;
+            };
+            // Without a ticket the number is the merge request's !iid
+            if merge_request.ticket_number.starts_with('!') {
+                self.status = Some("The merge request has no ticket".to_string());
+                return;
+            }
+            template.replace("{ticket}", &merge_request.ticket_number)
+        } else {
+            merge_request.github.clone()
+        };
+        self.open_url(&url);
+    }
+
+    fn open_url(&mut self, url: &str) {
+        if let Err(error) = browser::open(url) {
+            self.status = Some(format!("{:#}", error));
+        }
+    }
+
+    /// Selects another project, its merge request table starts at the top.
+    fn select_project(&mut self, index: usize) {
+        self.selected = index;
+        self.projects[index].changed = false;
+        self.merge_requests = TableState::default();
+    }
+
+    /// Moves the merge request selection by `rows`, negative moves up.
+    fn select_merge_request(&mut self, rows: isize) {
+        let count = self
+            .get_current_commit_status()
+            .map_or(0, |changelog| changelog.merge_requests.len());
+        if count == 0 {
+            return;
+        }
+        let selected = match self.merge_requests.selected() {
+            Some(selected) => selected.saturating_add_signed(rows).min(count - 1),
+            None => 0,
+        };
+        self.merge_requests.select(Some(selected));
+    }
+
+    /// Compares the selected project with the next one, or closes compare mode.
+    pub fn toggle_compare(&mut self) {
+        if self.compare.is_some() || self.projects.len() < 2 {
+            self.compare = None;
+            return;
+        }
+        self.compare = Some((self.selected, (self.selected + 1) % self.projects.len()));
+        self.compare_scroll = 0;
+    }
+
+    /// Replaces the right-hand project with the next (or previous) one that is not on the left.
+    fn cycle_compared(&mut self, forward: bool) {
+        let Some((left, right)) = self.compare else {
+            return;
+        };
+        let count = self.projects.len();
+        let step = if forward { 1 } else { count - 1 };
+        let mut next = (right + step) % count;
+        if next == left {
+            next = (next + step) % count;
+        }
+        self.compare = Some((left, next));
+        self.compare_scroll = 0;
+    }
+
+    fn scroll_compare(&mut self, down: bool, rows: usize) {
+        let Some((left, right)) = self.compare else {
+            return;
+        };
+        let length = |index: usize| {
+            return self.projects[index]
+                .changelog
+                .as_ref()
+                .map_or(0, |changelog| changelog.merge_requests.len());
+        };
+        let last_row = length(left).max(length(right)).saturating_sub(1);
+        self.compare_scroll = if down {
+            (self.compare_scroll + rows).min(last_row)
+        } else {
+            self.compare_scroll.saturating_sub(rows)
+        };
+    }
+
+    /// Handles a key press including macro recording and replay, returns true when the app
+    /// should quit.
+    pub fn press(&mut self, key: KeyEvent) -> bool {
+        self.status = None;
+        if let Some(prompt) = self.macros.prompt.take() {
+            let KeyCode::Char(name) = key.code else {
+                return false;
+            };
+            self.usage.feature("macros");
+            match prompt {
+                MacroPrompt::Record => self.macros.start_recording(name),
+                MacroPrompt::Replay => {
+                    for key in self.macros.keys(name) {
+                        if handle_key(self, key) {
+                            return true;
+                        }
+                    }
+                }
+            }
+            return false;
+        }
+        let ctrl_r =
+            key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl_r && self.macros.recording().is_some() {
+            if let Err(error) = self.macros.stop_recording() {
+                self.status = Some(format!("Could not save the macro: {}", error));
+            }
+            return false;
+        }
+        if ctrl_r {
+            self.macros.prompt = Some(MacroPrompt::Record);
+            return false;
+        }
+        // In the command palette @ is just part of the search
+        if key.code == KeyCode::Char('@') && self.palette.is_none() {
+            self.macros.prompt = Some(MacroPrompt::Replay);
+            return false;
+        }
+        self.macros.record(key);
+        let quit = handle_key(self, key);
+        self.note_screen();
+        return quit;
+    }
+
+    /// Remembers which screens were opened, for the telemetry.
+    fn note_screen(&mut self) {
+        let screens = [
+            ("screen:deployment", self.ready_for_deployment),
+            ("screen:history", self.show_history),
+            ("screen:inbox", self.show_inbox),
+            ("screen:approvals", self.show_approvals),
+            ("screen:compare", self.compare.is_some()),
+            ("screen:palette", self.palette.is_some()),
+        ];
+        for (screen, shown) in screens {
+            if shown {
+                self.usage.feature(screen);
+            }
+        }
+    }
+
+    fn history_len(&self) -> usize {
+        return self
+            .history
+            .as_ref()
+            .map_or(0, |history| history.records.len());
+    }
+
+    /// Fetches the changelog of a project again in the background, e.g. after a last minute
+    /// merge or when it failed to load.
+    pub fn refresh(&mut self, index: usize) {
+        let Some(fetcher) = &self.fetcher else {
+            return;
+        };
+        let project = &mut self.projects[index];
+        if project.refreshing || (project.changelog.is_none() && project.error.is_none()) {
+            return;
+        }
+        project.error = None;
+        project.refreshing = project.changelog.is_some();
+        fetcher.fetch(index);
+    }
+
+    pub fn refresh_all(&mut self) {
+        for index in 0..self.projects.len() {
+            self.refresh(index);
+        }
+    }
+
+    fn spinner(&self) -> char {
+        return SPINNER[self.tick % SPINNER.len()];
+    }
+}
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// How long to wait for input before redrawing, so progress of a running deployment shows up
+const TICK_RATE: Duration = Duration::from_millis(100);
+/// How long a finished deployment waits for the approvals before it is recorded without them
+const APPROVALS_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn run(mut terminal: DefaultTerminal, app: &mut App) -> Result<()> {
+    loop {
+        app.update();
+        if app.quitting && !app.deployment.deployment_running && app.recording.is_none() {
+            return Ok(());
+        }
+        terminal.draw(|f| render(f, app))?;
+        if !event::poll(TICK_RATE)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if app.press(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Reacts to a key press in the current view, returns true when the app should quit.
+fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    if let Some(confirmation) = app.confirmation.take() {
+        if key.code == KeyCode::Char('y') {
+            match confirmation {
+                Confirmation::CheckOption(index) => app.change(UiChange::ToggleOption(index)),
+                Confirmation::Undo(_) => app.apply_undo(),
+                Confirmation::Redo(_) => app.apply_redo(),
+                Confirmation::CancelDeployment => app.deployment.cancel(),
+                Confirmation::Quit => {
+                    app.deployment.cancel();
+                    app.quitting = true;
+                }
+            }
+        }
+        return false;
+    }
+    if app.deployment.held_step().is_some() {
+        let decision = match key.code {
+            KeyCode::Char('c') => Some(HoldDecision::Continue),
+            KeyCode::Char('e') => Some(HoldDecision::Extend),
+            KeyCode::Char('s') => Some(HoldDecision::Stop),
+            _ => None,
+        };
+        if let Some(decision) = decision {
+            app.deployment.decide(decision);
+            return false;
+        }
+    }
+    if app.deployment.paused_step().is_some() && !app.deployment.is_cancelled() {
+        let decision = match key.code {
+            KeyCode::Char('r') => Some(FailureDecision::Retry),
+            KeyCode::Char('s') => Some(FailureDecision::Skip),
+            _ => None,
+        };
+        if let Some(decision) = decision {
+            app.deployment.decide_failure(decision);
+            return false;
+        }
+    }
+    if app.palette.is_some() {
+        app.palette_key(key.code);
+        return false;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        match key.code {
+            KeyCode::Char('p') => app.palette = Some(CommandPalette::default()),
+            KeyCode::Char('z') => app.undo(),
+            KeyCode::Char('y') => app.redo(),
+            _ => {}
+        }
+        return false;
+    }
+    if key.code == KeyCode::Char('q') {
+        // Its commands would keep running and it would not be recorded
+        if app.deployment.deployment_running {
+            if !app.quitting {
+                app.confirmation = Some(Confirmation::Quit);
+            }
+            return false;
+        }
+        if app.recording.is_some() {
+            app.quitting = true;
+            return false;
+        }
+        return true;
+    }
+    if app.show_history {
+        match key.code {
+            KeyCode::Char('h') | KeyCode::Esc | KeyCode::Backspace => {
+                app.show_history = false;
+            }
+            KeyCode::Up if app.history_selected > 0 => app.history_selected -= 1,
+            KeyCode::Down if app.history_selected + 1 < app.history_len() => {
+                app.history_selected += 1;
+            }
+            _ => {}
+        }
+        return false;
+    }
+    if key.code == KeyCode::Char('h') {
+        app.show_history = true;
+        app.history_selected = 0;
+        return false;
+    }
+    if app.show_inbox {
+        match key.code {
+            KeyCode::Char('i') | KeyCode::Esc | KeyCode::Backspace => app.show_inbox = false,
+            KeyCode::Char('r') => app.refresh_inbox(),
+            KeyCode::Char('o') => {
+                if let Some(Ok(todos)) = &app.inbox.todos {
+                    if let Some(todo) = todos.get(app.inbox.selected) {
+                        let url = todo.target_url.clone();
+                        app.open_url(&url);
+                    }
+                }
+            }
+            KeyCode::Up if app.inbox.selected > 0 => app.inbox.selected -= 1,
+            KeyCode::Down if app.inbox.selected + 1 < app.inbox.count() => {
+                app.inbox.selected += 1;
+            }
+            _ => {}
+        }
+        return false;
+    }
+    if key.code == KeyCode::Char('i') {
+        app.open_inbox();
+        return false;
+    }
+    if app.show_approvals {
+        match key.code {
+            KeyCode::Char('a') | KeyCode::Esc | KeyCode::Backspace => {
+                app.show_approvals = false;
+            }
+            KeyCode::Char('r') => app.refresh_approvals(),
+            KeyCode::Char('o') => {
+                let summary = app
+                    .approvals
+                    .summaries
+                    .as_ref()
+                    .and_then(|summaries| summaries.get(app.approvals.selected));
+                if let Some(summary) = summary {
+                    let url = summary.link.clone();
+                    app.open_url(&url);
+                }
+            }
+            KeyCode::Up if app.approvals.selected > 0 => app.approvals.selected -= 1,
+            KeyCode::Down if app.approvals.selected + 1 < app.approvals.count() => {
+                app.approvals.selected += 1;
+            }
+            _ => {}
+        }
+        return false;
+    }
+    if key.code == KeyCode::Char('a') {
+        app.open_approvals();
+        return false;
+    }
+    if app.compare.is_some() {
+        match key.code {
+            KeyCode::Char('v') | KeyCode::Esc | KeyCode::Backspace => app.toggle_compare(),
+            KeyCode::Up => app.scroll_compare(false, 1),
+            KeyCode::Down => app.scroll_compare(true, 1),
+            KeyCode::PageUp => app.scroll_compare(false, LOG_SCROLL_LINES),
+            KeyCode::PageDown => app.scroll_compare(true, LOG_SCROLL_LINES),
+            KeyCode::Left => app.cycle_compared(false),
+            KeyCode::Right => app.cycle_compared(true),
+            _ => {}
+        }
+        return false;
+    }
+    if !app.ready_for_deployment {
+        match key.code {
+            KeyCode::Char('c') => app.ready_for_deployment = true,
+            KeyCode::Char('v') => app.toggle_compare(),
+            KeyCode::Backspace => app.ready_for_deployment = false,
+            KeyCode::Char('r') => app.refresh(app.selected),
+            KeyCode::Char('R') => app.refresh_all(),
+            KeyCode::Left if app.selected > 0 => app.select_project(app.selected - 1),
+            KeyCode::Right if app.selected + 1 < app.projects.len() => {
+                app.select_project(app.selected + 1);
+            }
+            KeyCode::Char('o') => app.open_merge_request(false),
+            KeyCode::Char('O') => app.open_merge_request(true),
+            KeyCode::Char('x') => app.exclude_selected(),
+            KeyCode::Up | KeyCode::Char('k') => app.select_merge_request(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.select_merge_request(1),
+            KeyCode::PageUp => app.select_merge_request(-(LOG_SCROLL_LINES as isize)),
+            KeyCode::PageDown => app.select_merge_request(LOG_SCROLL_LINES as isize),
+            _ => {}
+        }
+    } else {
+        match key.code {
+            KeyCode::Enter if app.all_changelogs_loaded() => app.start_deployment(),
+            // Configured steps may not have any options
+            KeyCode::Up if !app.deployment.selected_options.is_empty() => {
+                let options_count = app.deployment.selected_options.len();
+                app.deployment.current_option =
+                    (app.deployment.current_option + options_count - 1) % options_count;
+            }
+            KeyCode::Down if !app.deployment.selected_options.is_empty() => {
+                app.deployment.current_option =
+                    (app.deployment.current_option + 1) % app.deployment.selected_options.len();
+            }
+            KeyCode::PageUp => app.deployment.scroll_log_up(LOG_SCROLL_LINES),
+            KeyCode::PageDown => app.deployment.scroll_log_down(LOG_SCROLL_LINES),
+            KeyCode::Tab | KeyCode::Char(' ')
+                if app.deployment.current_option < app.deployment.selected_options.len() =>
+            {
+                app.toggle_option(app.deployment.current_option);
+            }
+            KeyCode::Char('d') => app.change(UiChange::ToggleDryRun),
+            KeyCode::Esc | KeyCode::Char('c')
+                if app.deployment.deployment_running && !app.deployment.is_cancelled() =>
+            {
+                app.confirmation = Some(Confirmation::CancelDeployment);
+            }
+            _ => {}
+        }
+    }
+    return false;
+}
+
+pub fn render(frame: &mut Frame, app: &mut App) {
+    if app.show_history {
+        render_history_view(frame, app);
+    } else if app.show_inbox {
+        render_inbox_view(frame, app);
+    } else if app.show_approvals {
+        render_approvals_view(frame, app);
+    } else if let Some((left, right)) = app.compare {
+        render_compare_view(frame, app, left, right);
+    } else if app.ready_for_deployment {
+        render_deployment_view(frame, app);
+    } else {
+        render_commit_overview(frame, app);
+    }
+    if app.tutorial.is_some() {
+        render_tutorial(frame, app);
+    }
+    if app.palette.is_some() {
+        render_palette(frame, app);
+    }
+    match app.confirmation {
+        Some(
+            Confirmation::CheckOption(index)
+            | Confirmation::Undo(index)
+            | Confirmation::Redo(index),
+        ) => {
+            let label = &app.deployment.selected_options[index].label;
+            render_popup(frame, &format!("Really check \"{}\"? (y/n)", label));
+        }
+        Some(Confirmation::CancelDeployment) => {
+            render_popup(frame, "Cancel the running deployment? (y/n)");
+        }
+        Some(Confirmation::Quit) => {
+            render_popup(frame, "A deployment is running, cancel it and quit? (y/n)");
+        }
+        None => {}
+    }
+    if app.quitting && app.deployment.deployment_running {
+        render_popup(frame, "Cancelling the deployment, quitting once it stopped");
+    } else if app.quitting {
+        render_popup(
+            frame,
+            "Recording the deployment, quitting once it is recorded",
+        );
+    }
+    if let Some(step) = app.deployment.held_step() {
+        render_popup(
+            frame,
+            &format!(
+                "The deployment ran past its time box, \"{}\" is on hold\n(c) Continue  (e) Extend  (s) Stop\nStopping does not roll back the steps that already ran",
+                step.label
+            ),
+        );
+    }
+    render_status(frame, app);
+}
+
+/// The current tutorial step in the top right corner, keys in backticks highlighted.
+fn render_tutorial(frame: &mut Frame, app: &App) {
+    let Some(tutorial) = &app.tutorial else {
+        return;
+    };
+    let step = tutorial.step();
+    let (position, count) = tutorial.progress();
+    let spans: Vec<Span> = step
+        .text
+        .split('`')
+        .enumerate()
+        .map(|(index, part)| {
+            // Every second part was between backticks
+            if index % 2 == 1 {
+                return Span::styled(
+                    part,
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+            }
+            return Span::raw(part);
+        })
+        .collect();
+    let area = frame.area();
+    let width = area.width.min(48);
+    let text_width = step.text.chars().count() as u16;
+    let height = (text_width / width.saturating_sub(4).max(1) + 4).min(area.height);
+    let area = Rect::new(area.right() - width, area.top() + 1, width, height);
+    let block = Block::bordered()
+        .title(format!(" Tutorial {}/{}: {} ", position, count, step.title))
+        .style(Style::default().fg(Color::White).bg(Color::Blue));
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(Line::from(spans))
+            .wrap(Wrap { trim: true })
+            .block(block),
+        area,
+    );
+}
+
+/// Centered box on top of the current view, sized to fit the text.
+fn render_popup(frame: &mut Frame, text: &str) {
+    let width = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let [area] = Layout::vertical([Constraint::Length(text.lines().count() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::horizontal([Constraint::Length(width as u16 + 4)])
+        .flex(Flex::Center)
+        .areas(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text)
+            .centered()
+            .block(Block::bordered().style(Style::default().fg(Color::Yellow))),
+        area,
+    );
+}
+
+/// Macro recording state or the last status message in the bottom right corner.
+fn render_status(frame: &mut Frame, app: &App) {
+    let text = match (&app.status, app.macros.prompt, app.macros.recording()) {
+        (Some(status), _, _) => status.clone(),
+        (None, Some(MacroPrompt::Record), _) => "Record macro into key…".to_string(),
+        (None, Some(MacroPrompt::Replay), _) => "Replay macro from key…".to_string(),
+        (None, None, Some(name)) => format!("● Recording macro {} (Ctrl+R to stop)", name),
+        (None, None, None) => return,
+    };
+    let area = frame.area();
+    let width = (text.chars().count() as u16).min(area.width);
+    let area = Rect::new(
+        area.right() - width,
+        area.bottom().saturating_sub(1),
+        width,
+        1,
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text).style(Style::default().fg(Color::Red)),
+        area,
+    );
+}
+
+/// Lines PageUp/PageDown move the deployment log.
+const LOG_SCROLL_LINES: usize = 10;
+
+/// Up to this many projects are shown side by side, more switch to tabs.
+const MAX_SIDE_BY_SIDE_PROJECTS: usize = 2;
+
+fn render_commit_overview(frame: &mut Frame, app: &mut App) {
+    let tabbed = app.projects.len() > MAX_SIDE_BY_SIDE_PROJECTS;
+    let outer_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Max(if tabbed { 6 } else { 5 }),
+            Constraint::Length(1),
+            Constraint::Min(1),
+        ])
+        .split(frame.area());
+
+    if tabbed {
+        render_project_tabs(frame, app, outer_layout[0]);
+    } else {
+        render_project_panes(frame, app, outer_layout[0]);
+    }
+
+    let composition = render_composition(app);
+    frame.render_widget(composition, outer_layout[1]);
+
+    // Borders and the header row take up three lines of the table area
+    let visible_rows = outer_layout[2].height.saturating_sub(3) as usize;
+    let count = app
+        .get_current_commit_status()
+        .map_or(0, |changelog| changelog.merge_requests.len());
+    let selected = app
+        .merge_requests
+        .selected()
+        .map(|selected| selected.min(count.saturating_sub(1)));
+    // Keep the selected row in view, scrolling as little as possible
+    let mut offset = app
+        .merge_requests
+        .offset()
+        .min(count.saturating_sub(visible_rows));
+    if let Some(selected) = selected {
+        if selected < offset {
+            offset = selected;
+        } else if visible_rows > 0 && selected >= offset + visible_rows {
+            offset = selected + 1 - visible_rows;
+        }
+    }
+    *app.merge_requests.offset_mut() = offset;
+
+    // Only the visible rows are handed to the table, so the state is relative to them
+    let mut state = TableState::default().with_selected(selected.map(|selected| selected - offset));
+    let commit = render_commit_section(app, offset, visible_rows);
+    frame.render_stateful_widget(commit, outer_layout[2], &mut state);
+
+    let mut scrollbar_state =
+        ScrollbarState::new(count.saturating_sub(visible_rows)).position(offset);
+    frame.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight),
+        outer_layout[2].inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut scrollbar_state,
+    );
+}
+
+/// Two projects' merge requests side by side, scrolled together. Tickets that appear in
+/// both changelogs are highlighted.
+fn render_compare_view(frame: &mut Frame, app: &App, left: usize, right: usize) {
+    let layout = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).split(frame.area());
+    let tables = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(layout[0]);
+
+    let tickets = |index: usize| -> HashSet<&str> {
+        return app.projects[index]
+            .changelog
+            .iter()
+            .flat_map(|changelog| changelog.merge_requests.iter())
+            .map(|merge_request| merge_request.ticket_number.as_str())
+            // Merge requests without a ticket fall back to their per-project !iid
+            .filter(|ticket| !ticket.starts_with('!'))
+            .collect();
+    };
+    let left_tickets = tickets(left);
+    let right_tickets = tickets(right);
+    let shared: HashSet<&str> = left_tickets.intersection(&right_tickets).copied().collect();
+
+    // Borders and the header row take up three lines of the table area
+    let visible_rows = tables[0].height.saturating_sub(3) as usize;
+    for (area, index) in [(tables[0], left), (tables[1], right)] {
+        let table = render_compare_table(
+            &app.projects[index],
+            &shared,
+            app.compare_scroll,
+            visible_rows,
+        );
+        frame.render_widget(table, area);
+    }
+    let hint =
+        "Up/Down/PageUp/PageDown scroll both, Left/Right change the right project, v to close";
+    frame.render_widget(
+        Paragraph::new(hint).style(Style::default().fg(Color::DarkGray)),
+        layout[1],
+    );
+}
+
+fn render_compare_table<'a>(
+    project: &'a Project,
+    shared: &HashSet<&str>,
+    scroll: usize,
+    visible_rows: usize,
+) -> Table<'a> {
+    let mut title = project.config.title.clone();
+    if let Some(changelog) = &project.changelog {
+        title += &format!(
+            " v{} ({} MRs, {} shared tickets)",
+            changelog.next_version_number,
+            changelog.merge_requests.len(),
+            shared.len()
+        );
+    }
+    let rows = project
+        .changelog
+        .iter()
+        .flat_map(|changelog| changelog.merge_requests.iter())
+        .skip(scroll)
+        .take(visible_rows)
+        .map(|merge_request| {
+            let row = Row::new(vec![
+                merge_request.ticket_number.as_str(),
+                merge_request.title.as_str(),
+            ]);
+            if shared.contains(merge_request.ticket_number.as_str()) {
+                return row.style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+            }
+            return row;
+        });
+    return Table::new(rows, [Constraint::Length(10), Constraint::Min(10)])
+        .header(
+            Row::new(vec!["Ticket", "Description"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(title));
+}
+
+fn render_project_panes(frame: &mut Frame, app: &App, area: Rect) {
+    let mut constraints = vec![];
+    for index in 0..app.projects.len() {
+        if index > 0 {
+            constraints.push(Constraint::Length(1));
+        }
+        constraints.push(Constraint::Min(3));
+    }
+    let sections = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(area);
+
+    for (index, project) in app.projects.iter().enumerate() {
+        let pane = render_commit_view(project, app.selected == index, app.spinner());
+        // Every pane but the first is preceded by a spacer
+        frame.render_widget(pane, sections[index * 2]);
+    }
+}
+
+fn render_project_tabs(frame: &mut Frame, app: &App, area: Rect) {
+    let layout = Layout::vertical([Constraint::Length(1), Constraint::Min(3)]).split(area);
+    let titles = app.projects.iter().map(|project| {
+        if project.changed {
+            return Line::from(format!("{} *", project.config.title))
+                .style(Style::default().fg(Color::Green));
+        }
+        return Line::from(project.config.title.as_str());
+    });
+    let tabs = Tabs::new(titles)
+        .select(app.selected)
+        .highlight_style(Style::default().fg(Color::Yellow));
+
+    let project = &app.projects[app.selected];
+    let pane = render_commit_view(project, true, app.spinner());
+
+    frame.render_widget(tabs, layout[0]);
+    frame.render_widget(pane, layout[1]);
+}
+
+fn render_deployment_view(frame: &mut Frame, app: &App) {
+    let hint = if app.deployment.paused_step().is_some() {
+        "(r) Retry step  (s) Skip step  (Esc/c) Cancel deployment"
+    } else if app.deployment.deployment_running {
+        "(Esc/c) Cancel deployment  (Space) Toggle option"
+    } else {
+        "(Enter) Start deployment  (Space) Toggle option  (d) Toggle dry run"
+    };
+    let mut block = Block::bordered()
+        .title(Line::from("Deployment").centered())
+        .title_bottom(
+            Line::from(hint)
+                .style(Style::default().fg(Color::Red))
+                .left_aligned(),
+        );
+    if app.deployment.dry_run {
+        block = block.title(
+            Line::from(" Dry run, nothing is deployed ")
+                .style(Style::default().fg(Color::Black).bg(Color::Yellow))
+                .left_aligned(),
+        );
+    }
+    if let Some(person) = &app.deployment.release_manager {
+        block = block.title(
+            Line::from(format!(" Release manager this week: {} ", person.name)).right_aligned(),
+        );
+    }
+
+    let layout = Layout::vertical([
+        Constraint::Percentage(40),
+        Constraint::Max(1),
+        Constraint::Min(1),
+    ])
+    .split(block.inner(frame.area()));
+    frame.render_widget(block, frame.area());
+
+    let mut items: Vec<String> = vec![];
+    let mut settings_state = ListState::default();
+    // Clear selection
+    for option in app.deployment.selected_options.iter() {
+        let label = if option.value {
+            "[x] ".to_string() + &option.label
+        } else {
+            "    ".to_owned() + &option.label
+        };
+        items.push(label);
+    }
+    let settings_list = List::new(items).highlight_style(Style::new().add_modifier(Modifier::BOLD));
+    settings_state.select(Some(app.deployment.current_option));
+    frame.render_stateful_widget(settings_list, layout[0], &mut settings_state);
+
+    let dry_run = if app.deployment.dry_run {
+        " (dry run)"
+    } else {
+        ""
+    };
+    let banner = if app.deployment.deployment_running && app.deployment.is_cancelled() {
+        Paragraph::new(format!("Cancelling deployment {}", app.spinner()))
+            .style(Style::default().bg(Color::Yellow).fg(Color::Black))
+    } else if app.deployment.paused_step().is_some() {
+        Paragraph::new("Step failed, retry it with r or skip it with s")
+            .style(Style::default().bg(Color::Red))
+    } else if app.deployment.deployment_running {
+        Paragraph::new(format!("Deployment running{}", dry_run))
+            .style(Style::default().bg(Color::Yellow).fg(Color::Black))
+    } else if app.projects.iter().any(|project| project.error.is_some()) {
+        Paragraph::new("Some changelogs could not be loaded, retry them in the overview")
+            .style(Style::default().fg(Color::Red))
+    } else if !app.all_changelogs_loaded() {
+        Paragraph::new(format!("Waiting for changelogs {}", app.spinner()))
+            .style(Style::default().fg(Color::DarkGray))
+    } else if app.deployment.is_cancelled() {
+        Paragraph::new("Deployment cancelled, press Enter to start again")
+            .style(Style::default().bg(Color::Red))
+    } else if app.deployment.has_failed() {
+        Paragraph::new("Deployment failed, press Enter to start again")
+            .style(Style::default().bg(Color::Red))
+    } else if app.deployment.has_finished() {
+        Paragraph::new(format!("Deployment finished{}", dry_run))
+            .style(Style::default().bg(Color::Green))
+    } else {
+        Paragraph::new(format!("Start deployment{}", dry_run))
+            .style(Style::default().bg(Color::Red))
+    };
+    frame.render_widget(banner.centered(), layout[1]);
+
+    let step_layout = Layout::vertical([
+        Constraint::Length(app.deployment.steps.len() as u16),
+        Constraint::Min(3),
+    ])
+    .split(layout[2]);
+
+    let mut items: Vec<ListItem> = vec![];
+    for step in app.deployment.steps.iter() {
+        let enabled = step
+            .option
+            .is_none_or(|option| app.deployment.selected_options[option].value);
+        let (symbol, style) = match &step.status {
+            StepStatus::Running => ("⏳ ", Style::default().fg(Color::Yellow)),
+            StepStatus::Succeeded => ("✅ ", Style::default().fg(Color::Green)),
+            StepStatus::Failed(_) => ("❌ ", Style::default().fg(Color::Red)),
+            StepStatus::TimedOut(_) => ("⌛ ", Style::default().fg(Color::LightRed)),
+            StepStatus::Held => ("⏸ ", Style::default().fg(Color::Magenta)),
+            StepStatus::Pending | StepStatus::Skipped => {
+                ("   ", Style::default().fg(Color::DarkGray))
+            }
+        };
+        let mut label = symbol.to_string() + &step.label;
+        if let Some(detail) = &step.detail {
+            label += &format!(" ({})", detail);
+        }
+        match &step.status {
+            StepStatus::Failed(message) => label += &format!(": {}", message),
+            StepStatus::TimedOut(message) => label += &format!(" [timed out]: {}", message),
+            StepStatus::Skipped => label += " [skipped]",
+            StepStatus::Held => label += " [on hold]",
+            StepStatus::Pending if !enabled => label += " [skipped]",
+            _ => {}
+        }
+        items.push(ListItem::new(label).style(style));
+    }
+    // The paused step is the one r and s act on
+    let mut steps_state = ListState::default();
+    steps_state.select(app.deployment.paused_index());
+    frame.render_stateful_widget(
+        List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        step_layout[0],
+        &mut steps_state,
+    );
+    frame.render_widget(render_log(app, step_layout[1].height), step_layout[1]);
+}
+
+/// Output of the running (or last) step's command, PageUp/PageDown scroll back.
+fn render_log(app: &App, height: u16) -> Paragraph<'_> {
+    let Some(step) = app.deployment.log_step() else {
+        return Paragraph::new("").block(Block::bordered().title("Log"));
+    };
+    let visible_rows = height.saturating_sub(2) as usize;
+    let scroll = app
+        .deployment
+        .log_scroll
+        .min(step.log.len().saturating_sub(visible_rows));
+    let end = step.log.len() - scroll;
+    let start = end.saturating_sub(visible_rows);
+    let mut title = format!("Log: {}", step.label);
+    if scroll > 0 {
+        title += &format!(" (scrolled up {} lines)", scroll);
+    }
+    if step.dropped_lines > 0 {
+        title += &format!(" ({} older lines dropped)", step.dropped_lines);
+    }
+    let lines: Vec<Line> = step
+        .log
+        .range(start..end)
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    return Paragraph::new(lines).block(Block::bordered().title(title));
+}
+
+/// The command palette as a popup in the middle of the screen.
+fn render_palette(frame: &mut Frame, app: &App) {
+    let Some(palette) = &app.palette else {
+        return;
+    };
+    let [area] = Layout::vertical([Constraint::Percentage(60)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::horizontal([Constraint::Percentage(60)])
+        .flex(Flex::Center)
+        .areas(area);
+    let block = Block::bordered().title("Search (Esc to close)");
+    let layout =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).split(block.inner(area));
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    frame.render_widget(Paragraph::new(format!("> {}", palette.query)), layout[0]);
+    let entries = app.palette_entries();
+    let matches = palette.matches(&entries);
+    let items: Vec<ListItem> = matches
+        .iter()
+        .map(|entry| ListItem::new(entry.label.as_str()))
+        .collect();
+    let mut state = ListState::default();
+    if !matches.is_empty() {
+        state.select(Some(palette.selected));
+    }
+    let list = List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, layout[1], &mut state);
+}
+
+/// GitLab to-dos of the tracked projects, e.g. requested approvals or failed pipelines.
+fn render_inbox_view(frame: &mut Frame, app: &App) {
+    let block = Block::bordered()
+        .title(Line::from("GitLab to-dos (i to go back, r to refresh)").centered());
+    let layout = Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+        .split(block.inner(frame.area()));
+    frame.render_widget(block, frame.area());
+
+    let message = |text: String, color: Color| {
+        return Paragraph::new(text).style(Style::default().fg(color));
+    };
+    if app.gitlab.is_none() {
+        let text = "Configure [gitlab] to see your to-dos".to_string();
+        frame.render_widget(message(text, Color::DarkGray), layout[0]);
+        return;
+    }
+    let todos = match &app.inbox.todos {
+        None => {
+            let text = format!("{} Loading to-dos", app.spinner());
+            frame.render_widget(message(text, Color::DarkGray), layout[0]);
+            return;
+        }
+        Some(Err(error)) => {
+            frame.render_widget(message(error.clone(), Color::Red), layout[0]);
+            return;
+        }
+        Some(Ok(todos)) if todos.is_empty() => {
+            let text = "Nothing to do for the tracked projects".to_string();
+            frame.render_widget(message(text, Color::Green), layout[0]);
+            return;
+        }
+        Some(Ok(todos)) => todos,
+    };
+
+    let items: Vec<ListItem> = todos
+        .iter()
+        .map(|todo| {
+            let project = todo
+                .project
+                .as_ref()
+                .map_or("", |project| project.name.as_str());
+            let color = match todo.action_name.as_str() {
+                "build_failed" | "unmergeable" => Color::Red,
+                "approval_required" | "review_requested" => Color::Yellow,
+                _ => Color::White,
+            };
+            let label = format!(
+                "{:<20} {}: {} ({})",
+                todo.reason(),
+                project,
+                todo.title(),
+                todo.author.name
+            );
+            return ListItem::new(label).style(Style::default().fg(color));
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.inbox.selected));
+    let list = List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, layout[0], &mut state);
+
+    let mut url = todos
+        .get(app.inbox.selected)
+        .map_or_else(String::new, |todo| todo.target_url.clone());
+    if app.inbox.is_loading() {
+        url = format!("{} Refreshing  {}", app.spinner(), url);
+    }
+    frame.render_widget(message(url, Color::DarkGray), layout[1]);
+}
+
+/// Who approved each included merge request, whether its code owners did and how many
+/// discussions are unresolved, the evidence compliance audits ask for.
+fn render_approvals_view(frame: &mut Frame, app: &App) {
+    let block = Block::bordered()
+        .title(Line::from("Approvals (a to go back, r to refresh, o to open)").centered());
+    let layout = Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+        .split(block.inner(frame.area()));
+    frame.render_widget(block, frame.area());
+
+    let message = |text: String, color: Color| {
+        return Paragraph::new(text).style(Style::default().fg(color));
+    };
+    if app.gitlab.is_none() {
+        let text = "Configure [gitlab] to see the approvals".to_string();
+        frame.render_widget(message(text, Color::DarkGray), layout[0]);
+        return;
+    }
+    let summaries = match &app.approvals.summaries {
+        None => {
+            let text = format!("{} Loading approvals", app.spinner());
+            frame.render_widget(message(text, Color::DarkGray), layout[0]);
+            return;
+        }
+        Some(summaries) if summaries.is_empty() => {
+            let text = "No merge requests in the release".to_string();
+            frame.render_widget(message(text, Color::DarkGray), layout[0]);
+            return;
+        }
+        Some(summaries) => summaries,
+    };
+
+    let rows = summaries.iter().map(|summary| {
+        let (approved_by, code_owners, unresolved) = match &summary.approvals {
+            Ok(approvals) => (
+                approvals.approved_by.join(", "),
+                match approvals.code_owners_approved {
+                    Some(true) => "approved",
+                    Some(false) => "missing",
+                    None => "-",
+                }
+                .to_string(),
+                approvals.unresolved_discussions.to_string(),
+            ),
+            Err(error) => (error.clone(), String::new(), String::new()),
+        };
+        let color = if summary.needs_attention() {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        return Row::new(vec![
+            summary.project.clone(),
+            summary.ticket.clone(),
+            summary.title.clone(),
+            approved_by,
+            code_owners,
+            unresolved,
+        ])
+        .style(Style::default().fg(color));
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Min(20),
+            Constraint::Min(20),
+            Constraint::Length(11),
+            Constraint::Length(10),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Project",
+            "Ticket",
+            "Title",
+            "Approved by",
+            "Code owners",
+            "Unresolved",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = TableState::default();
+    state.select(Some(app.approvals.selected));
+    frame.render_stateful_widget(table, layout[0], &mut state);
+
+    let attention = summaries
+        .iter()
+        .filter(|summary| summary.needs_attention())
+        .count();
+    let mut text = format!(
+        "{} of {} merge requests need attention",
+        attention,
+        summaries.len()
+    );
+    if app.approvals.is_loading() {
+        text = format!("{} Refreshing  {}", app.spinner(), text);
+    }
+    frame.render_widget(message(text, Color::DarkGray), layout[1]);
+}
+
+/// Past deployments, newest first, with the steps of the selected one.
+fn render_history_view(frame: &mut Frame, app: &App) {
+    let mut title = "Deployment history (h to go back)".to_string();
+    if let Some(error) = &app.history_error {
+        title += &format!(" - could not save the last deployment: {}", error);
+    }
+    let block = Block::bordered().title(Line::from(title).centered());
+    let layout = Layout::vertical([Constraint::Percentage(50), Constraint::Min(1)])
+        .split(block.inner(frame.area()));
+    frame.render_widget(block, frame.area());
+
+    let records: Vec<&DeploymentRecord> = match &app.history {
+        Some(history) => history.records.iter().rev().collect(),
+        None => vec![],
+    };
+    if records.is_empty() {
+        let message = if app.history.is_some() {
+            "No deployments yet"
+        } else {
+            "The deployment history is not available"
+        };
+        frame.render_widget(
+            Paragraph::new(message).style(Style::default().fg(Color::DarkGray)),
+            layout[0],
+        );
+        return;
+    }
+
+    let items: Vec<ListItem> = records
+        .iter()
+        .map(|record| {
+            let (result, color) = if record.has_failed() {
+                ("failed", Color::Red)
+            } else {
+                ("succeeded", Color::Green)
+            };
+            let mut label = format!(
+                "{}  {}  {}  {}",
+                record.display_time(),
+                record.user,
+                record.versions_summary(),
+                result
+            );
+            if record.dry_run {
+                label += " (dry run)";
+            }
+            return ListItem::new(label).style(Style::default().fg(color));
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.history_selected));
+    let list = List::new(items).highlight_style(Style::new().add_modifier(Modifier::BOLD));
+    frame.render_stateful_widget(list, layout[0], &mut state);
+
+    let record = records[app.history_selected.min(records.len() - 1)];
+    let mut lines = vec![Line::from(format!(
+        "Options: {}",
+        record.options.join(", ")
+    ))];
+    for step in record.steps.iter() {
+        lines.push(Line::from(format!("{}: {}", step.label, step.outcome)));
+    }
+    for artifact in record.artifacts.iter() {
+        lines.push(Line::from(artifact.to_string()).style(Style::default().fg(Color::Cyan)));
+    }
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Steps")),
+        layout[1],
+    );
+}
+
+fn render_commit_view(project: &Project, selected: bool, spinner: char) -> Paragraph<'_> {
+    let mut title = project.config.title.clone();
+    if project.refreshing {
+        title += &format!(" {}", spinner);
+    }
+    let mut block = Block::bordered().title(title).style(Style::default());
+    if project.changed {
+        block = block
+            .title(Line::from(" changed ").right_aligned())
+            .border_style(
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            );
+    }
+
+    let mut style = Style::default();
+    if selected {
+        style = style.fg(Color::Yellow);
+    }
+
+    if let Some(error) = &project.error {
+        let text = format!("{}\n(r) Retry", error);
+        return Paragraph::new(Text::styled(text, style.fg(Color::Red)))
+            .wrap(Wrap { trim: true })
+            .block(block);
+    }
+    let Some(changelog) = &project.changelog else {
+        let text = format!("{} Loading changelog", spinner);
+        return Paragraph::new(Text::styled(text, style)).block(block);
+    };
+
+    let version = match project.next_version() {
+        Some(version) => format!("Version {}", version),
+        None => "No release, every merge request is excluded".to_string(),
+    };
+    let text = format!(
+        "{} ({})\nCommit: {}({})\nAuthor: {}",
+        version,
+        changelog.current_time,
+        changelog.commit.title,
+        changelog.commit.commit_hash,
+        changelog.commit.author_name,
+    )
+    .to_string();
+    return Paragraph::new(Text::styled(text, style)).block(block);
+}
+
+fn render_composition<'a>(app: &App) -> Paragraph<'a> {
+    if app.get_current_commit_status().is_none() {
+        return Paragraph::new("");
+    }
+    let summary = app.get_current_composition().summary();
+    return Paragraph::new(summary).style(Style::default().fg(Color::Cyan));
+}
+
+/// Flags as colored badges, breaking changes and migrations in red. Every other flag keeps
+/// its color across merge requests.
+fn render_badges(flags: &[String]) -> Line<'_> {
+    const COLORS: [Color; 5] = [
+        Color::Blue,
+        Color::Green,
+        Color::Cyan,
+        Color::Magenta,
+        Color::Yellow,
+    ];
+    let mut spans = vec![];
+    for flag in flags.iter() {
+        let color = if is_breaking_flag(flag) {
+            Color::Red
+        } else {
+            let hash = flag.bytes().map(usize::from).sum::<usize>();
+            COLORS[hash % COLORS.len()]
+        };
+        spans.push(Span::styled(
+            format!(" {} ", flag),
+            Style::default().fg(Color::Black).bg(color),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    return Line::from(spans);
+}
+
+/// Only the rows that fit on screen are built, so frame time does not grow with the changelog size
+fn render_commit_section(app: &App, offset: usize, visible_rows: usize) -> Table<'_> {
+    let block = Block::bordered()
+        .title("Commit")
+        .title_bottom(
+            Line::from(
+                "(c) Move to deployment view  (v) Compare projects  (o/O) Open MR/ticket  (x) Exclude MR  (r/R) Refresh",
+            )
+                .style(Style::default().fg(Color::Red))
+                .left_aligned(),
+        )
+        .style(Style::default());
+
+    let project = &app.projects[app.selected];
+    let columns = visible_columns(&app.columns, &project.config);
+    let rows = project
+        .changelog
+        .iter()
+        .flat_map(|changelog| changelog.merge_requests.iter().enumerate())
+        .skip(offset)
+        .take(visible_rows)
+        .map(|(row, changelog)| {
+            // Borrow the cells, cloning every string on each frame is noticeable with hundreds of MRs
+            let mut cells = vec![
+                Cell::from(changelog.ticket_number.as_str()),
+                Cell::from(changelog.title.as_str()),
+                Cell::from(changelog.github.as_str()),
+                Cell::from(render_badges(&changelog.flags)),
+            ];
+            for column in columns.iter() {
+                let value = changelog
+                    .columns
+                    .get(&column.title)
+                    .map_or("", String::as_str);
+                cells.push(Cell::from(value));
+            }
+            let row_widget = Row::new(cells);
+            if project.is_excluded(row) {
+                return row_widget.style(
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT),
+                );
+            }
+            return row_widget;
+        });
+    let mut widths = vec![
+        Constraint::Length(8),
+        Constraint::Min(10),
+        Constraint::Min(20),
+        Constraint::Length(24),
+    ];
+    let mut header = vec![
+        "Ticket".to_string(),
+        "Description".to_string(),
+        "Gitlab".to_string(),
+        "Tags".to_string(),
+    ];
+    for column in columns.iter() {
+        widths.push(Constraint::Length(column.width));
+        header.push(column.title.clone());
+    }
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(block);
+
+    return table;
+}
@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::config::{Config, HistoryConfig, StepType};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The deployment would fail
+    Error,
+    /// Probably a mistake, but nothing breaks
+    Warning,
+}
+
+/// Something `steps lint` found in the config.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        return write!(f, "{}: {}", severity, self.message);
+    }
+}
+
+/// Checks the deployment steps without running anything: placeholders have to be known when
+/// the step runs, commands, secrets and hosts have to exist, and every step needs a way to
+/// run. Expects the step definitions to be resolved.
+pub fn lint(config: &Config, token_env: &str) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut error = |message: String| {
+        problems.push(Problem {
+            severity: Severity::Error,
+            message,
+        });
+    };
+
+    if std::env::var(token_env).is_err() {
+        error(format!("{} is not set", token_env));
+    }
+
+    check_placeholders(
+        &mut error,
+        "release_notes.template",
+        &config.release_notes.template,
+        &["title", "version", "summary", "merge_requests"],
+    );
+    check_placeholders(
+        &mut error,
+        "release_notes.merge_request_template",
+        &config.release_notes.merge_request_template,
+        &["ticket", "title", "link", "flags"],
+    );
+
+    if let Some(mail) = &config.mail {
+        check_placeholders(
+            &mut error,
+            "mail.subject",
+            &mail.subject,
+            &["versions", "date"],
+        );
+        if mail.username.is_some() && std::env::var("SMTP_PASSWORD").is_err() {
+            error("mail.username is set but SMTP_PASSWORD is not".to_string());
+        }
+        check_host(&mut error, "mail.host", &mail.host, mail.port);
+    }
+    match &config.history {
+        HistoryConfig::Postgres { url_env } if std::env::var(url_env).is_err() => {
+            error(format!("history.url_env is {}, which is not set", url_env));
+        }
+        HistoryConfig::S3 { endpoint, .. } => {
+            for variable in ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"] {
+                if std::env::var(variable).is_err() {
+                    error(format!(
+                        "the history is kept in S3 but {} is not set",
+                        variable
+                    ));
+                }
+            }
+            if let Some((host, port)) = host_of(endpoint) {
+                check_host(&mut error, "history.endpoint", &host, port);
+            }
+        }
+        _ => {}
+    }
+    if let Some(gitlab) = &config.gitlab {
+        if let Some((host, port)) = host_of(&gitlab.url) {
+            check_host(&mut error, "gitlab.url", &host, port);
+        }
+    }
+    for webhook in config.notifications.webhooks.iter() {
+        if let Some((host, port)) = host_of(webhook) {
+            check_host(&mut error, "notifications.webhooks", &host, port);
+        }
+    }
+
+    // What the steps produce for the later ones, in the order they run
+    let mut available: HashSet<String> = HashSet::new();
+    for step in config.step_list().iter() {
+        match &step.kind {
+            StepType::ReleaseNotes => {
+                if config.release_notes.path != "-" {
+                    available.insert("release_notes".to_string());
+                }
+                if config.release_notes.url.is_some() {
+                    available.insert("release_notes_url".to_string());
+                }
+            }
+            StepType::Mail => {}
+            StepType::Pipeline { project } => {
+                // Checked when the config was loaded
+                let Some(project) = config
+                    .projects
+                    .iter()
+                    .find(|config| &config.name == project)
+                else {
+                    continue;
+                };
+                let step = format!("the deployment of {}", project.name);
+                if project.deploy_command.is_empty() {
+                    if config.gitlab.is_none() {
+                        error(format!(
+                            "{} can never run: no deploy_command and no [gitlab] section",
+                            step
+                        ));
+                    }
+                    available.insert(format!("{}_pipeline", project.name));
+                    continue;
+                }
+                let mut known: Vec<&str> = vec!["project_id", "version"];
+                known.extend(available.iter().map(|name| name.as_str()));
+                for arg in project.deploy_command.iter() {
+                    check_placeholders(&mut error, &step, arg, &known);
+                }
+                check_command(&mut error, &step, &project.deploy_command[0]);
+                available.extend(
+                    project
+                        .extract
+                        .iter()
+                        .map(|extractor| extractor.name.clone()),
+                );
+            }
+            StepType::Shell { command, project } => {
+                let step = format!("the shell step {}", command.join(" "));
+                let Some(program) = command.first() else {
+                    error("a shell step has no command".to_string());
+                    continue;
+                };
+                let mut known: Vec<&str> = available.iter().map(|name| name.as_str()).collect();
+                if project.is_some() {
+                    known.extend(["project_id", "version"]);
+                }
+                for arg in command.iter() {
+                    check_placeholders(&mut error, &step, arg, &known);
+                }
+                check_command(&mut error, &step, program);
+            }
+            StepType::Tag {
+                project,
+                tag,
+                message,
+                repository,
+                ..
+            } => {
+                let step = format!("the tag step of {}", project);
+                let mut known: Vec<&str> = vec!["project_id", "version"];
+                known.extend(available.iter().map(|name| name.as_str()));
+                check_placeholders(&mut error, &step, tag, &known);
+                check_placeholders(&mut error, &step, message, &known);
+                match repository {
+                    Some(repository) => {
+                        if !repository.join(".git").exists() {
+                            error(format!(
+                                "{} uses {}, which is not a git checkout",
+                                step,
+                                repository.display()
+                            ));
+                        }
+                        check_command(&mut error, &step, "git");
+                    }
+                    None if config.gitlab.is_none() => error(format!(
+                        "{} can never run: no repository and no [gitlab] section",
+                        step
+                    )),
+                    None => {}
+                }
+                available.insert(format!("{}_tag", project));
+            }
+            // The site may well be built by an earlier step, so its directory is not checked
+            StepType::StaticSite(site) => {
+                let step = format!("the static site step of {}", site.source.display());
+                for variable in ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"] {
+                    if std::env::var(variable).is_err() {
+                        error(format!("{} needs {}, which is not set", step, variable));
+                    }
+                }
+                if let Some((host, port)) = host_of(&site.endpoint) {
+                    check_host(&mut error, &step, &host, port);
+                }
+            }
+            StepType::Webhook { url, message } => {
+                let known: Vec<&str> = available.iter().map(|name| name.as_str()).collect();
+                check_placeholders(&mut error, "a webhook step", message, &known);
+                if let Some((host, port)) = host_of(url) {
+                    check_host(&mut error, "a webhook step", &host, port);
+                }
+            }
+        }
+    }
+
+    let prints_notes = config.release_notes.path == "-"
+        && config
+            .step_list()
+            .iter()
+            .any(|step| matches!(step.kind, StepType::ReleaseNotes));
+    if prints_notes {
+        problems.push(Problem {
+            severity: Severity::Warning,
+            message: "release_notes.path = \"-\" only prints with --headless, the UI shows the \
+                      notes in the step's log"
+                .to_string(),
+        });
+    }
+
+    for name in config.steps.keys() {
+        let used = config
+            .projects
+            .iter()
+            .any(|project| project.uses.as_ref() == Some(name));
+        if !used {
+            problems.push(Problem {
+                severity: Severity::Warning,
+                message: format!("step {} is not used by any project", name),
+            });
+        }
+    }
+    return problems;
+}
+
+fn check_placeholders(error: &mut impl FnMut(String), field: &str, text: &str, known: &[&str]) {
+    let placeholder = Regex::new(r"\{([A-Za-z0-9_-]+)\}").unwrap();
+    for captures in placeholder.captures_iter(text) {
+        let name = &captures[1];
+        if !known.contains(&name) {
+            error(format!(
+                "{} uses {{{}}}, which is unknown when it runs",
+                field, name
+            ));
+        }
+    }
+}
+
+/// The command has to be a file, or found on the PATH.
+fn check_command(error: &mut impl FnMut(String), step: &str, command: &str) {
+    let found = if command.contains('/') {
+        Path::new(command).is_file()
+    } else {
+        std::env::var_os("PATH").is_some_and(|paths| {
+            std::env::split_paths(&paths).any(|path| path.join(command).is_file())
+        })
+    };
+    if !found {
+        error(format!("{} runs {}, which does not exist", step, command));
+    }
+}
+
+fn check_host(error: &mut impl FnMut(String), field: &str, host: &str, port: u16) {
+    if (host, port).to_socket_addrs().is_err() {
+        error(format!("{} {} cannot be resolved", field, host));
+    }
+}
+
+/// Host and port of an http(s) URL.
+fn host_of(url: &str) -> Option<(String, u16)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split('/').next()?;
+    let default_port = if scheme == "http" { 80 } else { 443 };
+    return match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None => Some((authority.to_string(), default_port)),
+    };
+}
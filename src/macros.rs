@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Waiting for the key that names the macro to record or replay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MacroPrompt {
+    Record,
+    Replay,
+}
+
+/// Recorded key sequences, bound to a key and stored per user as JSON.
+/// Ctrl+R followed by a key records into that key, Ctrl+R stops, `@` and the key replays.
+#[derive(Default)]
+pub struct Macros {
+    path: Option<PathBuf>,
+    /// Keys are stored readable, e.g. `["c", "down", "tab", "ctrl+z"]`
+    bindings: BTreeMap<char, Vec<String>>,
+    pub prompt: Option<MacroPrompt>,
+    recording: Option<(char, Vec<KeyEvent>)>,
+}
+
+/// `$XDG_DATA_HOME/deployment-tool/macros.json`, falling back to `~/.local/share`.
+pub fn default_path() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    return Some(data_home.join("deployment-tool").join("macros.json"));
+}
+
+impl Macros {
+    /// Reads the saved macros, a missing file means there are none yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bindings = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+            serde_json::from_str(&content)
+                .wrap_err_with(|| format!("Invalid macros in {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        return Ok(Self {
+            path: Some(path.to_path_buf()),
+            bindings,
+            prompt: None,
+            recording: None,
+        });
+    }
+
+    /// The key currently being recorded into.
+    pub fn recording(&self) -> Option<char> {
+        return self.recording.as_ref().map(|(name, _)| *name);
+    }
+
+    pub fn start_recording(&mut self, name: char) {
+        self.recording = Some((name, vec![]));
+    }
+
+    /// Adds a key press to the macro being recorded.
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key);
+        }
+    }
+
+    /// Binds the recorded keys and saves all macros.
+    pub fn stop_recording(&mut self) -> Result<()> {
+        let Some((name, keys)) = self.recording.take() else {
+            return Ok(());
+        };
+        let keys = keys.into_iter().filter_map(key_to_string).collect();
+        self.bindings.insert(name, keys);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)
+                .wrap_err_with(|| format!("Could not create {}", directory.display()))?;
+        }
+        return std::fs::write(path, serde_json::to_string_pretty(&self.bindings)?)
+            .wrap_err_with(|| format!("Could not write {}", path.display()));
+    }
+
+    /// The keys bound to `name`, empty if there is no such macro.
+    pub fn keys(&self, name: char) -> Vec<KeyEvent> {
+        return self
+            .bindings
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .filter_map(|key| parse_key(key))
+            .collect();
+    }
+}
+
+fn key_to_string(key: KeyEvent) -> Option<String> {
+    let name = match key.code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(character) => character.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        _ => return None,
+    };
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Some(format!("ctrl+{}", name));
+    }
+    return Some(name);
+}
+
+fn parse_key(key: &str) -> Option<KeyEvent> {
+    let (modifiers, name) = match key.strip_prefix("ctrl+") {
+        Some(name) => (KeyModifiers::CONTROL, name),
+        None => (KeyModifiers::NONE, key),
+    };
+    let code = match name {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "esc" => KeyCode::Esc,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => {
+            let mut characters = name.chars();
+            let character = characters.next()?;
+            if characters.next().is_some() {
+                return None;
+            }
+            KeyCode::Char(character)
+        }
+    };
+    return Some(KeyEvent::new(code, modifiers));
+}
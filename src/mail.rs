@@ -0,0 +1,79 @@
+use color_eyre::eyre::{bail, WrapErr};
+use color_eyre::Result;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use crate::config::{MailConfig, Person};
+use crate::Project;
+
+/// Environment variable with the password for `mail.username`.
+const PASSWORD_VARIABLE: &str = "SMTP_PASSWORD";
+
+/// Mails the release notes to the configured recipients, with the release manager in copy
+/// and a link to the published release notes if there is one.
+pub fn send_release_mail(
+    config: &MailConfig,
+    projects: &[Project],
+    notes: &str,
+    release_manager: Option<&Person>,
+    notes_url: Option<&str>,
+) -> Result<()> {
+    if config.recipients.is_empty() {
+        bail!("No release mail recipients configured");
+    }
+    let mut message = Message::builder()
+        .from(config.from.parse().wrap_err("Invalid sender address")?)
+        .subject(subject(config, projects))
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in config.recipients.iter() {
+        message = message.to(recipient
+            .parse()
+            .wrap_err_with(|| format!("Invalid recipient {}", recipient))?);
+    }
+    let mut body = notes.to_string();
+    if let Some(url) = notes_url {
+        body = format!("Release notes: {}\n\n{}", url, body);
+    }
+    if let Some(person) = release_manager {
+        body = format!("Release manager: {}\n\n{}", person.name, body);
+        if let Some(email) = &person.email {
+            message = message.cc(email
+                .parse()
+                .wrap_err_with(|| format!("Invalid email of {}", person.name))?);
+        }
+    }
+    let message = message.body(body)?;
+
+    let mut transport = SmtpTransport::starttls_relay(&config.host)
+        .wrap_err_with(|| format!("Could not connect to {}", config.host))?
+        .port(config.port);
+    if let Some(username) = &config.username {
+        let password = std::env::var(PASSWORD_VARIABLE)
+            .wrap_err_with(|| format!("{} not set", PASSWORD_VARIABLE))?;
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+    transport
+        .build()
+        .send(&message)
+        .wrap_err_with(|| format!("Could not send the release mail via {}", config.host))?;
+    return Ok(());
+}
+
+/// The subject template with `{versions}` and `{date}` replaced.
+pub fn subject(config: &MailConfig, projects: &[Project]) -> String {
+    let versions: Vec<String> = projects
+        .iter()
+        .filter_map(|project| {
+            let version = project.next_version()?;
+            return Some(format!("{} v{}", project.config.title, version));
+        })
+        .collect();
+    return config
+        .subject
+        .replace("{versions}", &versions.join(", "))
+        .replace(
+            "{date}",
+            &chrono::Local::now().format("%Y-%m-%d").to_string(),
+        );
+}
@@ -1,335 +1,255 @@
-use rayon::prelude::*;
-use serde::Deserialize;
+#![allow(clippy::needless_return)]
 
-use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode};
-use ratatui::{
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Text},
-    widgets::{Block, List, ListState, Paragraph, Row, Table},
-    DefaultTerminal, Frame,
-};
-
-#[derive(Debug, Deserialize)]
-pub struct CurrentCommit {
-    commit_hash: String,
-    title: String,
-    author_name: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct Changelog {
-    next_version_number: u32,
-    commit: CurrentCommit,
-    current_time: String,
-    merge_requests: Vec<MergeRequest>,
-}
-#[derive(Debug, Deserialize)]
-pub struct MergeRequest {
-    ticket_number: String,
-    title: String,
-    github: String,
-    flags: String,
-}
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-fn get_changelog_info(project_id: &str, token: &str) -> Changelog {
-    let output = std::process::Command::new("php")
-        .arg("/home/mamazu/packages/brille24/ecom-docker/www/sulu/etc/change_log_generator.php")
-        .arg("--format=json")
-        .arg("--projectId=".to_owned() + project_id)
-        .arg("--token=".to_owned() + token)
-        .output()
-        .expect("Failed to get change logs");
-    if !output.status.success() {
-        panic!("{}", String::from_utf8_lossy(&output.stderr).into_owned());
-    }
-    let output_content = String::from_utf8_lossy(&output.stdout);
-    return serde_json::from_str(&output_content).expect("JSON was not well-formatted");
-}
+use clap::Parser;
+use color_eyre::eyre::{bail, WrapErr};
+use color_eyre::Result;
+use deployment_tool::audit;
+use deployment_tool::cli::{AuditCommand, Cli, Command, StepsCommand};
+use deployment_tool::config::Config;
+use deployment_tool::deployment::Deployment;
+use deployment_tool::fetcher::ChangelogFetcher;
+use deployment_tool::gitlab::GitlabClient;
+use deployment_tool::headless;
+use deployment_tool::history::History;
+use deployment_tool::identity;
+use deployment_tool::lint::{self, Severity};
+use deployment_tool::macros::{self, Macros};
+use deployment_tool::session::Session;
+use deployment_tool::storage;
+use deployment_tool::telemetry::{self, Usage};
+use deployment_tool::tutorial::{self, Tutorial};
+use deployment_tool::watch;
+use deployment_tool::{print_changelog, run, App, Project};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
-    let terminal = ratatui::init();
-    let token = std::env::var("GITLAB_TOKEN").expect("GITLAB_TOKEN not set");
-    let mut app = App::new(
-        ["251", "65"]
-            .par_iter()
-            .map(|version| {
-                return get_changelog_info(version, &token);
-            })
-            .collect(),
-    );
-    let result = run(terminal, &mut app);
-    ratatui::restore();
-    result
-}
-
-#[derive(PartialEq)]
-pub enum SelectedBlock {
-    Left,
-    Right,
-}
-
-pub struct DeploymentOption {
-    value: bool,
-    label: String,
-}
-
-pub struct Deployment {
-    selected_options: Vec<DeploymentOption>,
-    current_option: usize,
-    deployment_running: bool,
-}
-
-impl Deployment {
-    pub fn new() -> Self {
-        return Self {
-            selected_options: vec![
-                DeploymentOption{ value: false, label: "Send Release Mail".to_string() },
-                DeploymentOption{ value: true, label: "Sylius Deployment".to_string() },
-                DeploymentOption{ value: true, label: "Sulu Deployment".to_string() },
-            ],
-            current_option: 0,
-            deployment_running: false,
-        };
+    let cli = Cli::parse();
+    // Needs neither a config nor a token
+    if cli.tutorial {
+        return tutorial();
     }
-}
-
-pub struct App {
-    pub selected: SelectedBlock,
-    pub ready_for_deployment: bool,
-    pub deployment: Deployment,
-    pub changelog: Vec<Changelog>,
-}
-
-impl App {
-    pub fn new(changelog: Vec<Changelog>) -> Self {
-        return Self {
-            selected: SelectedBlock::Left,
-            ready_for_deployment: false,
-            deployment: Deployment::new(),
-            changelog,
-        };
+    let config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::load_default()?,
+    };
+    let telemetry = config.telemetry.clone();
+    let mut usage = Usage::default();
+    usage.config(&config);
+    // Watching runs until it is stopped, so it reports right away
+    if let Some(telemetry) = telemetry.as_ref().filter(|_| cli.watch) {
+        usage.feature("mode:watch");
+        telemetry::send(telemetry, &usage);
     }
-
-    pub fn get_current_commit_status(&self) -> &Changelog {
-        match self.selected {
-            SelectedBlock::Left => &self.changelog[0],
-            SelectedBlock::Right => &self.changelog[1],
+    let result = start(&cli, config, &mut usage);
+    if let Some(telemetry) = telemetry.as_ref().filter(|_| !cli.watch) {
+        if let Err(error) = &result {
+            usage.fatal(error);
         }
+        telemetry::send(telemetry, &usage);
     }
-}
-
-fn run(mut terminal: DefaultTerminal, app: &mut App) -> Result<()> {
-    loop {
-        terminal.draw(|f| render(f, app))?;
-        match event::read()? {
-            Event::Key(key) => {
-                if key.code == KeyCode::Char('q') {
-                    return Ok(());
-                }
-                if !app.ready_for_deployment {
-                    match key.code {
-                        KeyCode::Char('c') => app.ready_for_deployment = true,
-                        KeyCode::Backspace => app.ready_for_deployment = false,
-                        KeyCode::Left => {
-                            if app.selected == SelectedBlock::Right {
-                                app.selected = SelectedBlock::Left;
-                            }
-                        }
-                        KeyCode::Right => {
-                            if app.selected == SelectedBlock::Left {
-                                app.selected = SelectedBlock::Right;
-                            }
-                        }
-                        _ => {}
-                    }
-                } else {
-                    match key.code {
-                        KeyCode::Enter => {
-                            app.deployment.deployment_running = true;
-                        }
-                        KeyCode::Char(' ') => {}
-                        KeyCode::Up => {
-                            let options_count = app.deployment.selected_options.len();
-                            app.deployment.current_option = (app.deployment.current_option + options_count - 1) % options_count;
-                        }
-                        KeyCode::Down => {
-                            app.deployment.current_option = (app.deployment.current_option + 1) % app.deployment.selected_options.len();
-                        }
-                        KeyCode::Tab => {
-                            app.deployment.selected_options[app.deployment.current_option].value = !app.deployment.selected_options[app.deployment.current_option].value;
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            _ => {}
-        }
+    return result;
+}
+
+/// Everything after loading the config, `usage` collects what the run used.
+fn start(cli: &Cli, mut config: Config, usage: &mut Usage) -> Result<()> {
+    // Only reads the history
+    if let Some(Command::Audit {
+        command: AuditCommand::Export { release, output },
+    }) = &cli.command
+    {
+        usage.feature("mode:audit_export");
+        return export_audit(&config, release, output.as_deref());
     }
-}
-
-fn render(frame: &mut Frame, app: &mut App) {
-    if app.ready_for_deployment {
-        render_deployment_view(frame, app);
-    } else {
-        render_commit_overview(frame, app);
+    config.select_projects(&cli.projects)?;
+    if let Some(Command::Steps {
+        command: StepsCommand::Lint,
+    }) = &cli.command
+    {
+        usage.feature("mode:steps_lint");
+        return lint_steps(&mut config, cli);
     }
-}
-
-fn render_commit_overview(frame: &mut Frame, app: &mut App) {
-    let outer_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Max(5), Constraint::Min(1)])
-        .split(frame.area());
-    let sections = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Min(3),
-            Constraint::Length(1),
-            Constraint::Min(3),
-        ])
-        .split(outer_layout[0]);
-
-    let left = render_commit_view(
-        "Sulu",
-        &app.changelog[0],
-        app.selected == SelectedBlock::Left,
-    );
-    let right = render_commit_view(
-        "Sylius",
-        &app.changelog[1],
-        app.selected == SelectedBlock::Right,
-    );
-    let commit = render_commit_section(app);
-
-    frame.render_widget(left, sections[0]);
-    frame.render_widget(right, sections[2]);
-    frame.render_widget(commit, outer_layout[1]);
-}
-
-fn render_deployment_view(frame: &mut Frame, app: &App) {
-    let block = Block::bordered().title(Line::from("Deployment").centered());
-
-    let layout = Layout::vertical([
-        Constraint::Percentage(40),
-        Constraint::Max(1),
-        Constraint::Min(1),
-    ])
-    .split(block.inner(frame.area()));
-    frame.render_widget(block, frame.area());
-
-    let mut items: Vec<String> = vec![];
-    let mut settings_state = ListState::default();
-    // Clear selection
-    for option in app.deployment.selected_options.iter() {
-        let label: String;
-        if option.value {
-            label = "[x] ".to_string()+&option.label;
-        } else {
-            label = "    ".to_owned()+&option.label;
+    // Without a terminal (CI, docker run without -t) there is nothing to draw on, and quietly
+    // printing instead would look like a deployment that succeeded
+    if !cli.headless && !cli.no_tui && !cli.watch && !std::io::stdout().is_terminal() {
+        bail!("stdout is not a terminal, pass --headless to deploy or --no-tui to print the changelogs");
+    }
+    let token =
+        std::env::var(&cli.token_env).wrap_err_with(|| format!("{} not set", cli.token_env))?;
+    config.resolve_steps(&token)?;
+    let config = Arc::new(config);
+    if cli.watch {
+        return watch::watch(config, &token);
+    }
+    let mut projects: Vec<Project> = config
+        .projects
+        .iter()
+        .map(|project| Project::loading(project.clone()))
+        .collect();
+    let fetcher = ChangelogFetcher::new(Arc::clone(&config), &token);
+    fetcher.fetch_all();
+
+    let gitlab = config
+        .gitlab
+        .as_ref()
+        .map(|gitlab| GitlabClient::new(&gitlab.url, &token));
+    let without_ui = cli.headless || cli.no_tui;
+    // Only printing the changelog records no deployment, so nobody has to log in for it
+    let deploys = cli.headless || !without_ui;
+    // Before the UI takes over the terminal, the login prints where to go
+    let identity = match &config.oidc {
+        Some(oidc) if deploys => Some(identity::login(oidc).wrap_err("OIDC login failed")?),
+        _ => None,
+    };
+    let mut history = None;
+    if deploys {
+        if let Some(store) = storage::open(&config.history)? {
+            history = Some(History::load(store)?);
         }
-        items.push(label);
     }
-    let settings_list = List::new(items)
-        .highlight_style(Style::new().add_modifier(Modifier::BOLD))
-    ;
-    settings_state.select(Some(app.deployment.current_option));
-    frame.render_stateful_widget(settings_list, layout[0], &mut settings_state);
 
-    if app.deployment.deployment_running {
-        let text = Paragraph::new("Deployment running")
-            .style(Style::default().bg(Color::Yellow).fg(Color::Black))
-            .centered();
-        frame.render_widget(text, layout[1]);
-    } else {
-        let text = Paragraph::new("Start deployment")
-            .style(Style::default().bg(Color::Red))
-            .centered();
-        frame.render_widget(text, layout[1]);
+    if without_ui {
+        for _ in 0..projects.len() {
+            let update = fetcher.wait();
+            let changelog = update.changelog.wrap_err_with(|| {
+                format!("Could not load {}", projects[update.project].config.title)
+            })?;
+            projects[update.project].set_changelog(changelog);
+        }
+        print_changelog(&projects);
+        if !cli.headless {
+            usage.feature("mode:print");
+            return Ok(());
+        }
+        usage.feature("mode:headless");
+        let mut deployment = Deployment::with_steps(&projects, &config.step_list());
+        configure(&mut deployment, &config, cli);
+        deployment.identity = identity;
+        let result = headless::deploy(
+            &mut deployment,
+            &projects,
+            gitlab.as_ref(),
+            history.as_mut(),
+        );
+        usage.deployment(&deployment);
+        return result;
     }
 
-    let mut send_release_mail = "Send release mail ".to_string();
-    if !app.deployment.selected_options[0].value {
-        send_release_mail += "[skipped]";
-    }
-    let items = [
-        "Generate release notes",
-        &send_release_mail,
-        "Starting Sylius Pipeline",
-        "Starting Sulu Pipeline",
-    ];
-    let mut deployment_style = Style::default();
-    if !app.deployment.deployment_running {
-        deployment_style = deployment_style.fg(Color::DarkGray);
+    let macros = match macros::default_path() {
+        Some(path) => Macros::load(&path)?,
+        None => Macros::default(),
+    };
+    let terminal = ratatui::init();
+    let mut app = App::new(projects, gitlab, Some(fetcher));
+    app.deployment = Deployment::with_steps(&app.projects, &config.step_list());
+    app.history = history;
+    app.ticket_url = config.ticket_url.clone();
+    app.columns = config.columns.clone();
+    if config.watch.auto_refresh {
+        app.auto_refresh = Some(Duration::from_secs(config.watch.interval));
     }
-    let mut state = ListState::default();
-    let list = List::new(items)
-        .style(deployment_style)
-        .highlight_style(Style::new().add_modifier(Modifier::BOLD))
-        .highlight_symbol("✅ ")
-        .repeat_highlight_symbol(true);
-    frame.render_stateful_widget(list, layout[2], &mut state);
+    app.restore(&Session::load());
+    app.macros = macros;
+    configure(&mut app.deployment, &config, cli);
+    app.deployment.identity = identity;
+    app.usage = std::mem::take(usage);
+    app.usage.feature("mode:ui");
+    let result = run(terminal, &mut app);
+    ratatui::restore();
+    *usage = std::mem::take(&mut app.usage);
+    result?;
+    return app.session().save();
 }
 
-fn render_commit_view<'a>(
-    title: &'static str,
-    changelog: &Changelog,
-    selected: bool,
-) -> Paragraph<'a> {
-    let block = Block::bordered().title(title).style(Style::default());
-
-    let mut style = Style::default();
-    if selected {
-        style = style.fg(Color::Yellow);
-    }
-
-    let text = format!(
-        "Version {} ({})\nCommit: {}({})\nAuthor: {}",
-        changelog.next_version_number, changelog.current_time,
-        changelog.commit.title, changelog.commit.commit_hash,
-        changelog.commit.author_name,
-    ).to_string();
-    return Paragraph::new(Text::styled(text, style)).block(block);
+/// Runs the UI with the demo projects, only dry runs and nothing is saved.
+fn tutorial() -> Result<()> {
+    let terminal = ratatui::init();
+    let mut app = App::new(tutorial::demo_projects(), None, None);
+    app.tutorial = Some(Tutorial::default());
+    app.deployment.dry_run = true;
+    let result = run(terminal, &mut app);
+    ratatui::restore();
+    return result;
 }
 
-fn render_commit_section(app: &App) -> Table {
-    let block = Block::bordered()
-        .title("Commit")
-        .title_bottom(
-            Line::from("(c) Move to deployment view")
-                .style(Style::default().fg(Color::Red))
-                .left_aligned(),
-        )
-        .style(Style::default());
-
-    let rows = app
-        .get_current_commit_status()
-        .merge_requests
+/// Writes the audit bundle of the newest real deployment that released the version.
+fn export_audit(config: &Config, release: &str, output: Option<&Path>) -> Result<()> {
+    let Some(store) = storage::open(&config.history)? else {
+        bail!("No deployment history, neither XDG_DATA_HOME nor HOME is set");
+    };
+    let history = History::load(store)?;
+    let Some(record) = history
+        .records
         .iter()
-        .map(|changelog| {
-            return Row::new(vec![
-                changelog.ticket_number.clone(),
-                changelog.title.clone(),
-                changelog.github.clone(),
-                changelog.flags.clone(),
-            ]);
-        });
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Length(8),
-            Constraint::Min(10),
-            Constraint::Min(20),
-            Constraint::Length(10),
-        ],
-    )
-    .header(
-        Row::new(vec!["Ticket", "Description", "Gitlab", "Tags"])
-            .style(Style::default().add_modifier(Modifier::BOLD)),
-    )
-    .block(block);
+        .rev()
+        .find(|record| record.released(release))
+    else {
+        bail!(
+            "No successful deployment of {} in {}",
+            release,
+            history.location()
+        );
+    };
+    let default_output = PathBuf::from(format!("audit-{}.zip", release));
+    let output = output.unwrap_or(&default_output);
+    audit::export(record, output)?;
+    println!(
+        "Wrote the audit bundle of {} ({}) to {}",
+        record.versions_summary(),
+        record.display_time(),
+        output.display()
+    );
+    return Ok(());
+}
 
-    return table;
+/// Prints every problem `lint::lint` finds, fails when one of them is an error.
+fn lint_steps(config: &mut Config, cli: &Cli) -> Result<()> {
+    let token = std::env::var(&cli.token_env).unwrap_or_default();
+    config.resolve_steps(&token)?;
+    let problems = lint::lint(config, &cli.token_env);
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    let errors = problems
+        .iter()
+        .filter(|problem| problem.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        bail!("{} errors found", errors);
+    }
+    println!("No errors found");
+    return Ok(());
+}
+
+/// Applies the config and command line options to the deployment, after a restored session
+/// so `--release-mail` wins.
+fn configure(deployment: &mut Deployment, config: &Config, cli: &Cli) {
+    deployment.dry_run = cli.dry_run;
+    deployment.release_notes = config.release_notes.clone();
+    deployment.mail = config.mail.clone();
+    deployment.notifications = config.notifications.clone();
+    deployment.time_box = config.time_box.clone();
+    deployment.cancel_pipelines = config
+        .gitlab
+        .as_ref()
+        .is_some_and(|gitlab| gitlab.cancel_pipelines);
+    deployment.pipeline_timeout = config
+        .gitlab
+        .as_ref()
+        .and_then(|gitlab| gitlab.pipeline_timeout_seconds)
+        .map(Duration::from_secs);
+    // Validated when the config was loaded
+    deployment.release_manager = config.rotation.as_ref().and_then(|rotation| {
+        rotation
+            .release_manager(chrono::Local::now().date_naive())
+            .ok()
+            .cloned()
+    });
+    if cli.release_mail {
+        deployment.set_release_mail(true);
+    }
 }
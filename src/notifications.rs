@@ -0,0 +1,110 @@
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+
+use crate::artifacts::Artifact;
+use crate::config::{NotificationsConfig, Person};
+use crate::Project;
+
+/// Posts deployment events to Slack or Teams incoming webhooks, both accept a JSON
+/// body with a `text` field.
+pub struct Notifier {
+    webhooks: Vec<String>,
+    agent: ureq::Agent,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationsConfig) -> Self {
+        return Self {
+            webhooks: config.webhooks.clone(),
+            agent: ureq::AgentBuilder::new().build(),
+        };
+    }
+
+    /// Sends the message to every webhook, the first error is returned after trying all.
+    pub fn notify(&self, message: &str) -> Result<()> {
+        let mut result = Ok(());
+        for webhook in self.webhooks.iter() {
+            let sent = self
+                .agent
+                .post(webhook)
+                .send_json(serde_json::json!({ "text": message }))
+                // ureq's error already names the URL
+                .wrap_err("Could not send the notification");
+            if let Err(error) = sent {
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
+        }
+        return result;
+    }
+}
+
+pub fn started_message(projects: &[&Project], release_manager: Option<&Person>) -> String {
+    let mut message = "Deployment started".to_string();
+    if let Some(person) = release_manager {
+        message += &format!(", release manager: {}", person.name);
+    }
+    for project in projects.iter() {
+        let Some(version) = project.next_version() else {
+            continue;
+        };
+        message += &format!("\n\n*{} v{}*", project.config.title, version);
+        for merge_request in project.included_merge_requests() {
+            message += &format!(
+                "\n- {} {} ({})",
+                merge_request.ticket_number, merge_request.title, merge_request.github
+            );
+        }
+    }
+    return message;
+}
+
+/// Names what the step produced, e.g. "Sulu v252 deployed (migrations = 3)".
+pub fn pipeline_finished_message(project: &Project, produced: &[Artifact]) -> String {
+    let version = project
+        .changelog
+        .as_ref()
+        .map_or(String::new(), |changelog| {
+            format!(" v{}", changelog.next_version_number)
+        });
+    let mut message = format!("{}{} deployed", project.config.title, version);
+    if !produced.is_empty() {
+        let values: Vec<String> = produced
+            .iter()
+            .map(|artifact| artifact.to_string())
+            .collect();
+        message += &format!(" ({})", values.join(", "));
+    }
+    return message;
+}
+
+pub fn held_message(step: &str) -> String {
+    return format!(
+        "Deployment on hold before \"{}\": it ran past its time box, continue, extend or stop it",
+        step
+    );
+}
+
+pub fn cancelled_message(step: &str) -> String {
+    return format!("Deployment cancelled at \"{}\"", step);
+}
+
+pub fn failed_message(step: &str, error: &str) -> String {
+    return format!("Deployment failed at \"{}\": {}", step, error);
+}
+
+pub fn finished_message(projects: &[&Project], release_manager: Option<&Person>) -> String {
+    let versions: Vec<String> = projects
+        .iter()
+        .filter_map(|project| {
+            let version = project.next_version()?;
+            return Some(format!("{} v{}", project.config.title, version));
+        })
+        .collect();
+    let mut message = format!("Deployment finished: {}", versions.join(", "));
+    if let Some(person) = release_manager {
+        message += &format!(" (release manager: {})", person.name);
+    }
+    return message;
+}
@@ -0,0 +1,80 @@
+/// What choosing a palette entry does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    StartDeployment,
+    ShowOverview,
+    ShowDeployment,
+    ShowHistory,
+    ShowInbox,
+    ShowApprovals,
+    /// Index into `Deployment.selected_options`
+    ToggleOption(usize),
+    ToggleDryRun,
+    /// Index into the projects
+    SelectProject(usize),
+    /// Project index and row of the merge request in its table
+    SelectMergeRequest(usize, usize),
+    /// Index into the history records, counted from the newest
+    SelectRelease(usize),
+}
+
+pub struct PaletteEntry {
+    pub label: String,
+    pub action: PaletteAction,
+}
+
+/// Ctrl+P search over actions and data of all views.
+#[derive(Default)]
+pub struct CommandPalette {
+    pub query: String,
+    /// Index into the matching entries
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    /// The entries matching the query, best match first.
+    pub fn matches<'a>(&self, entries: &'a [PaletteEntry]) -> Vec<&'a PaletteEntry> {
+        let mut scored: Vec<(i64, &PaletteEntry)> = entries
+            .iter()
+            .filter_map(|entry| Some((fuzzy_score(&self.query, &entry.label)?, entry)))
+            .collect();
+        // Stable, so equally good matches keep their order
+        scored.sort_by_key(|(score, _)| -score);
+        return scored.into_iter().map(|(_, entry)| entry).collect();
+    }
+
+    pub fn push(&mut self, character: char) {
+        self.query.push(character);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+}
+
+/// Scores how well `text` matches `query` when the query's characters appear in order,
+/// ignoring case. Consecutive characters and matches at word starts score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for character in query.to_lowercase().chars() {
+        if character.is_whitespace() {
+            continue;
+        }
+        let found = position + text[position..].iter().position(|&c| c == character)?;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    return Some(score);
+}
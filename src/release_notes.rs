@@ -0,0 +1,44 @@
+use crate::config::ReleaseNotesConfig;
+use crate::{is_breaking_flag, Project};
+
+/// Markdown release notes of every project, rendered with the configured templates.
+/// Excluded merge requests are left out.
+pub fn release_notes(projects: &[Project], config: &ReleaseNotesConfig) -> String {
+    let mut notes = String::new();
+    for project in projects.iter() {
+        let Some(version) = project.next_version() else {
+            continue;
+        };
+        let merge_requests: Vec<String> = project
+            .included_merge_requests()
+            .map(|merge_request| {
+                let flags = merge_request
+                    .flags
+                    .iter()
+                    .map(|flag| {
+                        if is_breaking_flag(flag) {
+                            return format!("**`{}`** ⚠️", flag);
+                        }
+                        return format!("`{}`", flag);
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                return config
+                    .merge_request_template
+                    .replace("{ticket}", &merge_request.ticket_number)
+                    .replace("{title}", &merge_request.title)
+                    .replace("{link}", &merge_request.github)
+                    .replace("{flags}", &flags)
+                    .trim_end()
+                    .to_string();
+            })
+            .collect();
+        notes += &config
+            .template
+            .replace("{title}", &project.config.title)
+            .replace("{version}", &version.to_string())
+            .replace("{summary}", &project.composition.summary())
+            .replace("{merge_requests}", &merge_requests.join("\n"));
+    }
+    return notes;
+}
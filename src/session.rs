@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum View {
+    #[default]
+    Overview,
+    Deployment,
+    History,
+}
+
+/// UI state saved on quit and restored on the next start. Projects and options are
+/// stored by name so they still match when the config or `--project` changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub view: View,
+    /// Name of the selected project
+    pub project: Option<String>,
+    /// Label of the highlighted deployment option
+    pub current_option: Option<String>,
+    /// Label and value of every deployment option
+    pub options: Vec<(String, bool)>,
+    /// Selected deployment in the history view, counted from the newest
+    pub history_selected: usize,
+    /// Selected row of the merge request table and how far it is scrolled
+    pub merge_request: Option<usize>,
+    pub merge_request_offset: usize,
+    /// How many lines the log pane is scrolled up
+    pub log_scroll: usize,
+}
+
+/// `$XDG_STATE_HOME/deployment-tool/session.json`, falling back to `~/.local/state`.
+pub fn default_path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    return Some(state_home.join("deployment-tool").join("session.json"));
+}
+
+impl Session {
+    /// The last saved session, a missing or unreadable file starts a fresh one.
+    pub fn load() -> Self {
+        let Some(path) = default_path() else {
+            return Self::default();
+        };
+        return std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = default_path() else {
+            return Ok(());
+        };
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)
+                .wrap_err_with(|| format!("Could not create {}", directory.display()))?;
+        }
+        return std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("Could not write {}", path.display()));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use color_eyre::Result;
+use regex::Regex;
+
+use crate::config::StaticSiteConfig;
+use crate::storage::{AwsClient, Bucket};
+
+/// How many files are uploaded at once.
+const UPLOAD_CONNECTIONS: usize = 8;
+/// CloudFront is a global service, it is always signed for this region.
+const CLOUDFRONT_ENDPOINT: &str = "https://cloudfront.amazonaws.com";
+const CLOUDFRONT_REGION: &str = "us-east-1";
+
+/// What a static site deployment changed.
+pub struct Upload {
+    pub uploaded: usize,
+    pub deleted: usize,
+    /// Id of the CloudFront invalidation
+    pub invalidation: Option<String>,
+}
+
+impl fmt::Display for Upload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "uploaded {} files", self.uploaded)?;
+        if self.deleted > 0 {
+            write!(f, ", removed {}", self.deleted)?;
+        }
+        if let Some(invalidation) = &self.invalidation {
+            write!(f, ", invalidation {}", invalidation)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Uploads every file of the site, removes the objects that are not part of it anymore when
+/// configured and then invalidates the distribution. `log` gets the name of every uploaded and
+/// removed object.
+pub fn deploy(
+    config: &StaticSiteConfig,
+    cancelled: &AtomicBool,
+    log: &(dyn Fn(String) + Sync),
+) -> Result<Upload> {
+    let files = site_files(&config.source)?;
+    let bucket = Bucket::new(&config.endpoint, &config.region, &config.bucket)?;
+    let chunk_size = files.len().div_ceil(UPLOAD_CONNECTIONS).max(1);
+    std::thread::scope(|scope| {
+        let uploads: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let bucket = &bucket;
+                return scope.spawn(move || {
+                    for file in chunk {
+                        if cancelled.load(Ordering::Relaxed) {
+                            bail!("Cancelled");
+                        }
+                        upload(bucket, config, file)?;
+                        log(format!("Uploaded {}{}", config.prefix, file));
+                    }
+                    return Ok(());
+                });
+            })
+            .collect();
+        for upload in uploads {
+            upload
+                .join()
+                .map_err(|_| eyre!("Uploading the site failed"))??;
+        }
+        return Ok::<(), color_eyre::Report>(());
+    })?;
+
+    let mut deleted = 0;
+    if config.delete {
+        let uploaded: HashSet<String> = files
+            .iter()
+            .map(|file| format!("{}{}", config.prefix, file))
+            .collect();
+        for key in bucket.list(&config.prefix)? {
+            if uploaded.contains(&key) {
+                continue;
+            }
+            if cancelled.load(Ordering::Relaxed) {
+                bail!("Cancelled");
+            }
+            bucket.delete(&key)?;
+            log(format!("Removed {}", key));
+            deleted += 1;
+        }
+    }
+
+    let invalidation = match &config.distribution_id {
+        Some(distribution) => Some(invalidate(distribution)?),
+        None => None,
+    };
+    return Ok(Upload {
+        uploaded: files.len(),
+        deleted,
+        invalidation,
+    });
+}
+
+/// Paths of every file in the directory and below, relative to it with `/` between the
+/// directories, in alphabetical order.
+pub fn site_files(source: &Path) -> Result<Vec<String>> {
+    let mut files = vec![];
+    let mut directories = vec![PathBuf::new()];
+    while let Some(directory) = directories.pop() {
+        let path = source.join(&directory);
+        let entries = std::fs::read_dir(&path)
+            .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let relative = directory.join(entry.file_name());
+            if entry.path().is_dir() {
+                directories.push(relative);
+                continue;
+            }
+            let parts: Vec<String> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            files.push(parts.join("/"));
+        }
+    }
+    files.sort();
+    return Ok(files);
+}
+
+fn upload(bucket: &Bucket, config: &StaticSiteConfig, file: &str) -> Result<()> {
+    let path = config.source.join(file);
+    let body =
+        std::fs::read(&path).wrap_err_with(|| format!("Could not read {}", path.display()))?;
+    let mut headers = vec![("Content-Type", content_type(file))];
+    if let Some(rule) = config
+        .cache_control
+        .iter()
+        .find(|rule| rule.pattern.is_match(file))
+    {
+        headers.push(("Cache-Control", rule.value.as_str()));
+    }
+    return bucket.put(&format!("{}{}", config.prefix, file), &body, &headers);
+}
+
+/// The type browsers need to show the file, by its extension.
+fn content_type(file: &str) -> &'static str {
+    let extension = file.rsplit_once('.').map_or("", |(_, extension)| extension);
+    match extension.to_lowercase().as_str() {
+        "html" | "htm" => return "text/html; charset=utf-8",
+        "css" => return "text/css; charset=utf-8",
+        "js" | "mjs" => return "text/javascript; charset=utf-8",
+        "json" | "map" => return "application/json",
+        "xml" => return "application/xml",
+        "txt" => return "text/plain; charset=utf-8",
+        "svg" => return "image/svg+xml",
+        "png" => return "image/png",
+        "jpg" | "jpeg" => return "image/jpeg",
+        "gif" => return "image/gif",
+        "webp" => return "image/webp",
+        "avif" => return "image/avif",
+        "ico" => return "image/x-icon",
+        "woff" => return "font/woff",
+        "woff2" => return "font/woff2",
+        "pdf" => return "application/pdf",
+        "webmanifest" => return "application/manifest+json",
+        _ => return "application/octet-stream",
+    }
+}
+
+/// Invalidates every path of the distribution and returns the id of the invalidation.
+fn invalidate(distribution: &str) -> Result<String> {
+    let client = AwsClient::new(CLOUDFRONT_ENDPOINT, CLOUDFRONT_REGION, "cloudfront")?;
+    // Unique, CloudFront treats a repeated reference as the same invalidation
+    let reference = chrono::Utc::now().format("deployment-tool-%Y%m%dT%H%M%S%.6fZ");
+    let body = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<InvalidationBatch xmlns="http://cloudfront.amazonaws.com/doc/2020-05-31/">"#,
+            "<Paths><Quantity>1</Quantity><Items><Path>/*</Path></Items></Paths>",
+            "<CallerReference>{}</CallerReference>",
+            "</InvalidationBatch>"
+        ),
+        reference
+    );
+    let response = client.request(
+        "POST",
+        &format!("/2020-05-31/distribution/{}/invalidation", distribution),
+        &[],
+        &[("Content-Type", "application/xml")],
+        body.as_bytes(),
+    )?;
+    let text = response.into_string()?;
+    let Some(captures) = Regex::new(r"<Id>([^<]*)</Id>").unwrap().captures(&text) else {
+        bail!("CloudFront did not say which invalidation it created");
+    };
+    return Ok(captures[1].to_string());
+}
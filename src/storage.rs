@@ -0,0 +1,848 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use color_eyre::Result;
+use regex::Regex;
+use ring::{digest, hmac};
+
+use crate::config::HistoryConfig;
+use crate::history::{self, DeploymentRecord};
+
+/// How many objects the S3 history downloads at once.
+const LOAD_CONNECTIONS: usize = 8;
+
+/// Where finished deployments are kept.
+pub trait HistoryStore: Send {
+    /// Every stored deployment, oldest first.
+    fn load(&mut self) -> Result<Vec<DeploymentRecord>>;
+
+    /// Stores one more deployment.
+    fn append(&mut self, record: &DeploymentRecord) -> Result<()>;
+
+    /// Where the deployments are, for messages.
+    fn location(&self) -> String;
+}
+
+/// Connects to the configured storage. `None` for the default file when there is no place
+/// for it.
+pub fn open(config: &HistoryConfig) -> Result<Option<Box<dyn HistoryStore>>> {
+    let store: Box<dyn HistoryStore> = match config {
+        HistoryConfig::File { path } => {
+            let Some(path) = path.clone().or_else(history::default_path) else {
+                return Ok(None);
+            };
+            Box::new(JsonFile { path })
+        }
+        HistoryConfig::Sqlite { path } => Box::new(Sqlite::open(path)?),
+        HistoryConfig::Postgres { url_env } => {
+            let url = std::env::var(url_env).wrap_err_with(|| format!("{} not set", url_env))?;
+            Box::new(Postgres::connect(&url)?)
+        }
+        HistoryConfig::S3 {
+            endpoint,
+            region,
+            bucket,
+            prefix,
+        } => Box::new(S3::new(endpoint, region, bucket, prefix)?),
+    };
+    return Ok(Some(store));
+}
+
+/// All deployments as one JSON array.
+struct JsonFile {
+    path: PathBuf,
+}
+
+impl HistoryStore for JsonFile {
+    /// A missing file is an empty history.
+    fn load(&mut self) -> Result<Vec<DeploymentRecord>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .wrap_err_with(|| format!("Could not read {}", self.path.display()))?;
+        return serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Invalid deployment history in {}", self.path.display()));
+    }
+
+    /// Writes the whole history back to disk.
+    fn append(&mut self, record: &DeploymentRecord) -> Result<()> {
+        let mut records = self.load()?;
+        records.push(record.clone());
+        if let Some(directory) = self.path.parent() {
+            std::fs::create_dir_all(directory)
+                .wrap_err_with(|| format!("Could not create {}", directory.display()))?;
+        }
+        let content = serde_json::to_string_pretty(&records)?;
+        return std::fs::write(&self.path, content)
+            .wrap_err_with(|| format!("Could not write {}", self.path.display()));
+    }
+
+    fn location(&self) -> String {
+        return self.path.display().to_string();
+    }
+}
+
+/// A `deployments` table with the record as JSON, next to the columns worth querying.
+struct Sqlite {
+    path: PathBuf,
+    connection: rusqlite::Connection,
+}
+
+impl Sqlite {
+    fn open(path: &Path) -> Result<Self> {
+        if let Some(directory) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(directory)
+                .wrap_err_with(|| format!("Could not create {}", directory.display()))?;
+        }
+        let connection = rusqlite::Connection::open(path)
+            .wrap_err_with(|| format!("Could not open {}", path.display()))?;
+        connection
+            .execute(
+                "CREATE TABLE IF NOT EXISTS deployments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    finished_at TEXT NOT NULL,
+                    deployed_by TEXT NOT NULL,
+                    record TEXT NOT NULL
+                )",
+                (),
+            )
+            .wrap_err_with(|| {
+                format!("Could not create the history table in {}", path.display())
+            })?;
+        return Ok(Self {
+            path: path.to_path_buf(),
+            connection,
+        });
+    }
+}
+
+impl HistoryStore for Sqlite {
+    fn load(&mut self) -> Result<Vec<DeploymentRecord>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT record FROM deployments ORDER BY id")?;
+        let rows = statement
+            .query_map((), |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .wrap_err_with(|| format!("Could not read the history from {}", self.location()))?;
+        return rows
+            .iter()
+            .map(|row| {
+                return serde_json::from_str(row).wrap_err_with(|| {
+                    format!("Invalid deployment history in {}", self.location())
+                });
+            })
+            .collect();
+    }
+
+    fn append(&mut self, record: &DeploymentRecord) -> Result<()> {
+        self.connection
+            .execute(
+                "INSERT INTO deployments (finished_at, deployed_by, record) VALUES (?1, ?2, ?3)",
+                (
+                    &record.finished_at,
+                    &record.user,
+                    serde_json::to_string(record)?,
+                ),
+            )
+            .wrap_err_with(|| format!("Could not write {}", self.location()))?;
+        return Ok(());
+    }
+
+    fn location(&self) -> String {
+        return self.path.display().to_string();
+    }
+}
+
+/// Like `Sqlite`, but the record is `jsonb` so it can be queried across installations.
+struct Postgres {
+    client: postgres::Client,
+}
+
+impl Postgres {
+    /// Uses TLS when the server offers it, `sslmode=require` in the connection string insists
+    /// on it.
+    fn connect(url: &str) -> Result<Self> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let mut client =
+            postgres::Client::connect(url, tokio_postgres_rustls::MakeRustlsConnect::new(tls))
+                .wrap_err("Could not connect to the history database")?;
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS deployments (
+                    id BIGSERIAL PRIMARY KEY,
+                    finished_at TIMESTAMPTZ NOT NULL,
+                    deployed_by TEXT NOT NULL,
+                    record JSONB NOT NULL
+                )",
+            )
+            .wrap_err("Could not create the history table")?;
+        return Ok(Self { client });
+    }
+}
+
+impl HistoryStore for Postgres {
+    fn load(&mut self) -> Result<Vec<DeploymentRecord>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT record::text FROM deployments ORDER BY finished_at, id",
+                &[],
+            )
+            .wrap_err("Could not read the history from the database")?;
+        return rows
+            .iter()
+            .map(|row| {
+                return serde_json::from_str(row.get(0))
+                    .wrap_err("Invalid deployment history in the database");
+            })
+            .collect();
+    }
+
+    fn append(&mut self, record: &DeploymentRecord) -> Result<()> {
+        self.client
+            .execute(
+                "INSERT INTO deployments (finished_at, deployed_by, record)
+                 VALUES ($1::text::timestamptz, $2, $3::text::jsonb)",
+                &[
+                    &record.finished_at,
+                    &record.user,
+                    &serde_json::to_string(record)?,
+                ],
+            )
+            .wrap_err("Could not write to the history database")?;
+        return Ok(());
+    }
+
+    fn location(&self) -> String {
+        return "the history database".to_string();
+    }
+}
+
+/// One object per deployment, so installations never overwrite each other. The object names
+/// start with the UTC finish time, listing them gives the order.
+struct S3 {
+    bucket: Bucket,
+    prefix: String,
+}
+
+impl S3 {
+    fn new(endpoint: &str, region: &str, bucket: &str, prefix: &str) -> Result<Self> {
+        return Ok(Self {
+            bucket: Bucket::new(endpoint, region, bucket)?,
+            prefix: prefix.to_string(),
+        });
+    }
+
+    fn get_record(&self, key: &str) -> Result<DeploymentRecord> {
+        return self
+            .bucket
+            .get(key)?
+            .into_json()
+            .wrap_err_with(|| format!("Invalid deployment history in {}", key));
+    }
+}
+
+impl HistoryStore for S3 {
+    /// Downloads the objects on several connections at once, one after the other the startup
+    /// would take longer with every deployment.
+    fn load(&mut self) -> Result<Vec<DeploymentRecord>> {
+        let keys: Vec<String> = self
+            .bucket
+            .list(&self.prefix)?
+            .into_iter()
+            .filter(|key| key.ends_with(".json"))
+            .collect();
+        let chunk_size = keys.len().div_ceil(LOAD_CONNECTIONS).max(1);
+        let store = &*self;
+        return std::thread::scope(|scope| {
+            // Each thread takes the next few keys, so the order stays
+            let downloads: Vec<_> = keys
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    return scope.spawn(move || {
+                        return chunk
+                            .iter()
+                            .map(|key| store.get_record(key))
+                            .collect::<Result<Vec<DeploymentRecord>>>();
+                    });
+                })
+                .collect();
+            let mut records = vec![];
+            for download in downloads {
+                let chunk = download
+                    .join()
+                    .map_err(|_| eyre!("Downloading the history failed"))??;
+                records.extend(chunk);
+            }
+            return Ok(records);
+        });
+    }
+
+    fn append(&mut self, record: &DeploymentRecord) -> Result<()> {
+        let finished_at = chrono::DateTime::parse_from_rfc3339(&record.finished_at)
+            .map_or(chrono::Utc::now(), |time| time.to_utc());
+        let user: String = record
+            .user
+            .chars()
+            .map(|char| {
+                if char.is_ascii_alphanumeric() || "._-".contains(char) {
+                    return char;
+                }
+                return '_';
+            })
+            .collect();
+        let key = format!(
+            "{}{}-{}.json",
+            self.prefix,
+            finished_at.format("%Y%m%dT%H%M%S%.6fZ"),
+            user
+        );
+        self.bucket
+            .put(&key, serde_json::to_string(record)?.as_bytes(), &[])?;
+        return Ok(());
+    }
+
+    fn location(&self) -> String {
+        return format!("s3://{}/{}", self.bucket.name, self.prefix);
+    }
+}
+
+/// Objects of an S3 bucket, or of a bucket in another storage with an S3 compatible API.
+pub(crate) struct Bucket {
+    client: AwsClient,
+    name: String,
+}
+
+impl Bucket {
+    pub(crate) fn new(endpoint: &str, region: &str, name: &str) -> Result<Self> {
+        return Ok(Self {
+            client: AwsClient::new(endpoint, region, "s3")?,
+            name: name.to_string(),
+        });
+    }
+
+    /// The bucket itself for an empty key.
+    fn path(&self, key: &str) -> String {
+        if key.is_empty() {
+            return format!("/{}", self.name);
+        }
+        return format!("/{}/{}", self.name, key);
+    }
+
+    /// Names of all objects starting with the prefix, in alphabetical order.
+    pub(crate) fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        return list_pages(|continuation| {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if let Some(token) = continuation {
+                query.push(("continuation-token", token));
+            }
+            let response = self
+                .client
+                .request("GET", &self.path(""), &query, &[], b"")?;
+            return Ok(response.into_string()?);
+        });
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Result<ureq::Response> {
+        return self.client.request("GET", &self.path(key), &[], &[], b"");
+    }
+
+    /// Uploads the object, `headers` are stored with it, e.g. its `Content-Type`.
+    pub(crate) fn put(&self, key: &str, body: &[u8], headers: &[(&str, &str)]) -> Result<()> {
+        self.client
+            .request("PUT", &self.path(key), &[], headers, body)?;
+        return Ok(());
+    }
+
+    pub(crate) fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .request("DELETE", &self.path(key), &[], &[], b"")?;
+        return Ok(());
+    }
+}
+
+/// An AWS service at an endpoint, with the credentials from `AWS_ACCESS_KEY_ID`,
+/// `AWS_SECRET_ACCESS_KEY` and, if set, `AWS_SESSION_TOKEN`.
+pub(crate) struct AwsClient {
+    agent: ureq::Agent,
+    endpoint: String,
+    /// Host and port of the endpoint, it is signed
+    host: String,
+    region: String,
+    /// e.g. `s3`, as named in the signature's scope
+    service: &'static str,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl AwsClient {
+    pub(crate) fn new(endpoint: &str, region: &str, service: &'static str) -> Result<Self> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let Some((_, host)) = endpoint.split_once("://") else {
+            bail!("The {} endpoint {} is not a URL", service, endpoint);
+        };
+        return Ok(Self {
+            agent: ureq::AgentBuilder::new().build(),
+            endpoint: endpoint.to_string(),
+            host: host.to_string(),
+            region: region.to_string(),
+            service,
+            access_key: std::env::var("AWS_ACCESS_KEY_ID").wrap_err("AWS_ACCESS_KEY_ID not set")?,
+            secret_key: std::env::var("AWS_SECRET_ACCESS_KEY")
+                .wrap_err("AWS_SECRET_ACCESS_KEY not set")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        });
+    }
+
+    /// Sends a request signed with AWS signature version 4. The path is encoded here,
+    /// `headers` are sent without being signed.
+    pub(crate) fn request(
+        &self,
+        method: &str,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<ureq::Response> {
+        let path = canonical_path(path);
+        let query = canonical_query(query);
+
+        let now = chrono::Utc::now();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex(digest::digest(&digest::SHA256, body).as_ref());
+        // Sorted by name, as the signature wants them
+        let mut signed = vec![
+            ("host", self.host.clone()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", timestamp.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            signed.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_request = canonical_request(method, &path, &query, &signed, &payload_hash);
+        let scope = Scope {
+            date: now.format("%Y%m%d").to_string(),
+            region: &self.region,
+            service: self.service,
+        };
+        let signature = signature(
+            &self.secret_key,
+            &scope,
+            &string_to_sign(&timestamp, &scope, &canonical_request),
+        );
+
+        let url = if query.is_empty() {
+            format!("{}{}", self.endpoint, path)
+        } else {
+            format!("{}{}?{}", self.endpoint, path, query)
+        };
+        let mut request = self.agent.request(method, &url).set(
+            "Authorization",
+            &format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key,
+                scope,
+                signed_headers(&signed),
+                signature
+            ),
+        );
+        for (name, value) in signed.iter() {
+            request = request.set(name, value);
+        }
+        for (name, value) in headers.iter() {
+            request = request.set(name, value);
+        }
+        return match request.send_bytes(body) {
+            Ok(response) => Ok(response),
+            // AWS explains what went wrong in the body
+            Err(ureq::Error::Status(status, response)) => {
+                let text = response.into_string().unwrap_or_default();
+                let message = Regex::new(r"<Message>([^<]*)</Message>")
+                    .unwrap()
+                    .captures(&text)
+                    .map_or(String::new(), |captures| format!(": {}", &captures[1]));
+                Err(eyre!(
+                    "{} {} failed with {}{}",
+                    method,
+                    url,
+                    status,
+                    message
+                ))
+            }
+            Err(error) => Err(error).wrap_err_with(|| format!("Could not reach {}", self.endpoint)),
+        };
+    }
+}
+
+/// What a signature is valid for.
+struct Scope<'a> {
+    /// e.g. 20130524
+    date: String,
+    region: &'a str,
+    service: &'a str,
+}
+
+impl fmt::Display for Scope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "{}/{}/{}/aws4_request",
+            self.date, self.region, self.service
+        );
+    }
+}
+
+/// The path with every segment percent-encoded.
+fn canonical_path(path: &str) -> String {
+    return path
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<String>>()
+        .join("/");
+}
+
+/// The encoded query parameters sorted by name and value.
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut query: Vec<(String, String)> = query
+        .iter()
+        .map(|(name, value)| (uri_encode(name), uri_encode(value)))
+        .collect();
+    query.sort();
+    return query
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<String>>()
+        .join("&");
+}
+
+/// Names of the headers, which have to be lowercase and sorted.
+fn signed_headers(headers: &[(&str, String)]) -> String {
+    return headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(";");
+}
+
+/// The request as the signature sees it, path and query already canonical.
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, String)],
+    payload_hash: &str,
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    return format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        path,
+        query,
+        canonical_headers,
+        signed_headers(headers),
+        payload_hash
+    );
+}
+
+fn string_to_sign(timestamp: &str, scope: &Scope, canonical_request: &str) -> String {
+    return format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+}
+
+/// Signs with a key derived from the secret for the scope.
+fn signature(secret_key: &str, scope: &Scope, string_to_sign: &str) -> String {
+    let mut key = format!("AWS4{}", secret_key).into_bytes();
+    for part in [
+        scope.date.as_str(),
+        scope.region,
+        scope.service,
+        "aws4_request",
+    ] {
+        key = sign(&key, part.as_bytes());
+    }
+    return hex(&sign(&key, string_to_sign.as_bytes()));
+}
+
+fn sign(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    return hmac::sign(&key, data).as_ref().to_vec();
+}
+
+fn hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+/// Percent-encodes everything but unreserved characters, as the signature expects.
+fn uri_encode(text: &str) -> String {
+    return text
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                return (byte as char).to_string();
+            }
+            return format!("%{:02X}", byte);
+        })
+        .collect();
+}
+
+/// Follows the continuation tokens of ListObjectsV2 until the last page, `fetch` gets the
+/// token of the page to load.
+fn list_pages(mut fetch: impl FnMut(Option<&str>) -> Result<String>) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut continuation: Option<String> = None;
+    loop {
+        let (page, next) = parse_list_page(&fetch(continuation.as_deref())?);
+        keys.extend(page);
+        match next {
+            Some(token) => continuation = Some(token),
+            None => return Ok(keys),
+        }
+    }
+}
+
+/// The object names of a ListObjectsV2 response and the token of the next page, if there is
+/// one.
+fn parse_list_page(text: &str) -> (Vec<String>, Option<String>) {
+    let key = Regex::new(r"<Key>([^<]*)</Key>").unwrap();
+    let next = Regex::new(r"<NextContinuationToken>([^<]*)</NextContinuationToken>").unwrap();
+    let keys = key
+        .captures_iter(text)
+        .map(|captures| xml_unescape(&captures[1]))
+        .collect();
+    let token = next
+        .captures(text)
+        .map(|captures| xml_unescape(&captures[1]));
+    return (keys, token);
+}
+
+/// Resolves the entities S3 uses in its XML, named ones and character references.
+fn xml_unescape(text: &str) -> String {
+    let entity = Regex::new(r"&(lt|gt|quot|apos|amp|#[0-9]+|#x[0-9a-fA-F]+);").unwrap();
+    return entity
+        .replace_all(text, |captures: &regex::Captures| {
+            let name = &captures[1];
+            let resolved = match name {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "amp" => Some('&'),
+                _ => match name.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => name[1..].parse().ok(),
+                }
+                .and_then(char::from_u32),
+            };
+            return resolved.map_or(captures[0].to_string(), String::from);
+        })
+        .into_owned();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_PAYLOAD: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    /// Canonical request, string to sign and signature of a GET request.
+    fn sign_get(
+        secret_key: &str,
+        scope: &Scope,
+        timestamp: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, String)],
+    ) -> (String, String, String) {
+        let request = canonical_request(
+            "GET",
+            &canonical_path("/"),
+            &canonical_query(query),
+            headers,
+            EMPTY_PAYLOAD,
+        );
+        let string_to_sign = string_to_sign(timestamp, scope, &request);
+        let signature = signature(secret_key, scope, &string_to_sign);
+        return (request, string_to_sign, signature);
+    }
+
+    /// get-vanilla of the AWS signature version 4 test suite.
+    #[test]
+    fn signs_the_vanilla_request() {
+        let scope = Scope {
+            date: "20150830".to_string(),
+            region: "us-east-1",
+            service: "service",
+        };
+        let headers = [
+            ("host", "example.amazonaws.com".to_string()),
+            ("x-amz-date", "20150830T123600Z".to_string()),
+        ];
+        let (request, string_to_sign, signature) = sign_get(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            &scope,
+            "20150830T123600Z",
+            &[],
+            &headers,
+        );
+        assert_eq!(
+            request,
+            format!(
+                "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\n\
+                 host;x-amz-date\n{}",
+                EMPTY_PAYLOAD
+            )
+        );
+        assert_eq!(
+            string_to_sign,
+            "AWS4-HMAC-SHA256\n20150830T123600Z\n20150830/us-east-1/service/aws4_request\n\
+             bb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63"
+        );
+        assert_eq!(
+            signature,
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    /// get-vanilla-query-order-key-case of the test suite: parameters are sorted by name.
+    #[test]
+    fn signs_the_query_sorted() {
+        let scope = Scope {
+            date: "20150830".to_string(),
+            region: "us-east-1",
+            service: "service",
+        };
+        let headers = [
+            ("host", "example.amazonaws.com".to_string()),
+            ("x-amz-date", "20150830T123600Z".to_string()),
+        ];
+        let (request, _, signature) = sign_get(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            &scope,
+            "20150830T123600Z",
+            &[("Param2", "value2"), ("Param1", "value1")],
+            &headers,
+        );
+        assert!(request.starts_with("GET\n/\nParam1=value1&Param2=value2\n"));
+        assert_eq!(
+            signature,
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    /// The "GET Bucket (List Objects)" example of the S3 documentation, with the headers this
+    /// backend sends.
+    #[test]
+    fn signs_the_s3_list_example() {
+        let scope = Scope {
+            date: "20130524".to_string(),
+            region: "us-east-1",
+            service: "s3",
+        };
+        let headers = [
+            ("host", "examplebucket.s3.amazonaws.com".to_string()),
+            ("x-amz-content-sha256", EMPTY_PAYLOAD.to_string()),
+            ("x-amz-date", "20130524T000000Z".to_string()),
+        ];
+        let (request, string_to_sign, signature) = sign_get(
+            "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+            &scope,
+            "20130524T000000Z",
+            &[("max-keys", "2"), ("prefix", "J")],
+            &headers,
+        );
+        assert_eq!(
+            request,
+            format!(
+                "GET\n/\nmax-keys=2&prefix=J\nhost:examplebucket.s3.amazonaws.com\n\
+                 x-amz-content-sha256:{0}\nx-amz-date:20130524T000000Z\n\n\
+                 host;x-amz-content-sha256;x-amz-date\n{0}",
+                EMPTY_PAYLOAD
+            )
+        );
+        assert_eq!(
+            string_to_sign,
+            "AWS4-HMAC-SHA256\n20130524T000000Z\n20130524/us-east-1/s3/aws4_request\n\
+             df57d21db20da04d7fa30298dd4488ba3a2b47ca3a489c74750e0f1e7df1b9b7"
+        );
+        assert_eq!(
+            signature,
+            "34b48302e7b5fa45bde8084f4b7868a86f0a534bc59db6670ed5711ef69dc6f7"
+        );
+    }
+
+    #[test]
+    fn encodes_keys_and_query_values() {
+        assert_eq!(
+            canonical_path("/history/shop team/2026+1.json"),
+            "/history/shop%20team/2026%2B1.json"
+        );
+        assert_eq!(
+            canonical_query(&[("prefix", "shop/"), ("continuation-token", "a=b")]),
+            "continuation-token=a%3Db&prefix=shop%2F"
+        );
+    }
+
+    #[test]
+    fn follows_the_continuation_tokens() {
+        let pages = [
+            "<ListBucketResult><IsTruncated>true</IsTruncated>\
+             <Contents><Key>shop-20260301T100000.000000Z-anna.json</Key></Contents>\
+             <Contents><Key>shop-20260302T100000.000000Z-ben.json</Key></Contents>\
+             <NextContinuationToken>1/ab&amp;c=</NextContinuationToken></ListBucketResult>",
+            "<ListBucketResult><IsTruncated>false</IsTruncated>\
+             <Contents><Key>shop-20260303T100000.000000Z-a&amp;b &lt;c&gt;&#x0D;.json</Key>\
+             </Contents></ListBucketResult>",
+        ];
+        let mut tokens = vec![];
+        let keys = list_pages(|token| {
+            tokens.push(token.map(String::from));
+            return Ok(pages[tokens.len() - 1].to_string());
+        })
+        .unwrap();
+        assert_eq!(tokens, [None, Some("1/ab&c=".to_string())]);
+        assert_eq!(
+            keys,
+            [
+                "shop-20260301T100000.000000Z-anna.json",
+                "shop-20260302T100000.000000Z-ben.json",
+                "shop-20260303T100000.000000Z-a&b <c>\r.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn an_empty_listing_has_no_keys() {
+        let (keys, token) = parse_list_page(
+            "<ListBucketResult><KeyCount>0</KeyCount><IsTruncated>false</IsTruncated>\
+             </ListBucketResult>",
+        );
+        assert!(keys.is_empty());
+        assert_eq!(token, None);
+    }
+
+    #[test]
+    fn unescapes_only_known_entities() {
+        assert_eq!(xml_unescape("&amp;lt; &#38; &unknown;"), "&lt; & &unknown;");
+    }
+}
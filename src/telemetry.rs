@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+
+use crate::config::{Config, HistoryConfig, StepType, TelemetryConfig};
+use crate::deployment::{Deployment, StepKind, StepStatus};
+use crate::session;
+
+/// How long sending the report may delay the exit.
+const SEND_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Which features a run used and how often which kind of error happened. Only fixed names
+/// end up in here, never project names, URLs, versions, users or command output.
+#[derive(Debug, Default, Serialize)]
+pub struct Usage {
+    /// e.g. `mode:headless`, `gitlab`, `step:pipeline` or `screen:history`
+    features: BTreeSet<String>,
+    /// e.g. `pipeline:timed_out` or `fatal:network`
+    errors: BTreeMap<String, u32>,
+}
+
+/// What is posted as JSON to the endpoint.
+#[derive(Serialize)]
+struct Report<'a> {
+    /// Random, to tell installations apart without knowing whose they are
+    installation: String,
+    version: &'static str,
+    os: &'static str,
+    #[serde(flatten)]
+    usage: &'a Usage,
+}
+
+impl Usage {
+    pub fn feature(&mut self, name: &str) {
+        self.features.insert(name.to_string());
+    }
+
+    pub fn error(&mut self, category: &str) {
+        *self.errors.entry(category.to_string()).or_default() += 1;
+    }
+
+    /// The features the config turns on.
+    pub fn config(&mut self, config: &Config) {
+        let mut features = vec![];
+        if let Some(gitlab) = &config.gitlab {
+            features.push("gitlab");
+            if gitlab.changelog {
+                features.push("gitlab:changelog");
+            }
+            if gitlab.cancel_pipelines {
+                features.push("gitlab:cancel_pipelines");
+            }
+            if gitlab.pipeline_timeout_seconds.is_some() {
+                features.push("gitlab:pipeline_timeout");
+            }
+        }
+        let history = match config.history {
+            HistoryConfig::File { .. } => "history:file",
+            HistoryConfig::Sqlite { .. } => "history:sqlite",
+            HistoryConfig::Postgres { .. } => "history:postgres",
+            HistoryConfig::S3 { .. } => "history:s3",
+        };
+        features.push(history);
+        let configured = [
+            ("ticket_url", config.ticket_url.is_some()),
+            ("oidc", config.oidc.is_some()),
+            ("mail", config.mail.is_some()),
+            ("release_notes:url", config.release_notes.url.is_some()),
+            ("webhooks", !config.notifications.webhooks.is_empty()),
+            ("watch:auto_refresh", config.watch.auto_refresh),
+            ("rotation", config.rotation.is_some()),
+            ("time_box", config.time_box.is_some()),
+            ("include", !config.include.is_empty()),
+            ("step_definitions", !config.steps.is_empty()),
+            ("columns", !config.columns.is_empty()),
+            ("deployment_steps", !config.deployment_steps.is_empty()),
+        ];
+        features.extend(
+            configured
+                .iter()
+                .filter(|(_, used)| *used)
+                .map(|(name, _)| *name),
+        );
+        let projects = [
+            (
+                "deploy_command",
+                config
+                    .projects
+                    .iter()
+                    .any(|project| !project.deploy_command.is_empty()),
+            ),
+            (
+                "extract",
+                config
+                    .projects
+                    .iter()
+                    .any(|project| !project.extract.is_empty()),
+            ),
+            (
+                "confirm",
+                config.projects.iter().any(|project| project.confirm),
+            ),
+        ];
+        features.extend(
+            projects
+                .iter()
+                .filter(|(_, used)| *used)
+                .map(|(name, _)| *name),
+        );
+        for step in config.step_list().iter() {
+            features.push(match step.kind {
+                StepType::ReleaseNotes => "config:release_notes",
+                StepType::Mail => "config:mail",
+                StepType::Pipeline { .. } => "config:pipeline",
+                StepType::Shell { .. } => "config:shell",
+                StepType::Webhook { .. } => "config:webhook",
+                StepType::Tag { .. } => "config:tag",
+                StepType::StaticSite(_) => "config:static_site",
+            });
+            if step.depends_on.is_some() {
+                features.push("depends_on");
+            }
+            if step.timeout_seconds.is_some() {
+                features.push("timeouts");
+            }
+            if step.retries > 0 {
+                features.push("retries");
+            }
+        }
+        for feature in features {
+            self.feature(feature);
+        }
+    }
+
+    /// The steps a finished deployment ran and how the failed ones failed.
+    pub fn deployment(&mut self, deployment: &Deployment) {
+        if deployment.dry_run {
+            self.feature("dry_run");
+        }
+        for step in deployment.steps.iter() {
+            let kind = step_category(&step.kind);
+            match &step.status {
+                StepStatus::Succeeded => self.feature(&format!("step:{}", kind)),
+                StepStatus::Failed(error) if error == "Cancelled" => self.feature("cancel"),
+                StepStatus::Failed(_) => self.error(&format!("{}:failed", kind)),
+                StepStatus::TimedOut(_) => self.error(&format!("{}:timed_out", kind)),
+                StepStatus::Pending
+                | StepStatus::Running
+                | StepStatus::Held
+                | StepStatus::Skipped => {}
+            }
+        }
+    }
+
+    /// An error that ended the run, by what caused it.
+    pub fn fatal(&mut self, error: &color_eyre::Report) {
+        let category = if error.chain().any(|cause| cause.is::<ureq::Error>()) {
+            "network"
+        } else if error
+            .chain()
+            .any(|cause| cause.is::<rusqlite::Error>() || cause.is::<postgres::Error>())
+        {
+            "database"
+        } else if error.chain().any(|cause| cause.is::<serde_json::Error>()) {
+            "invalid_data"
+        } else if error.chain().any(|cause| cause.is::<std::io::Error>()) {
+            "io"
+        } else {
+            "other"
+        };
+        self.error(&format!("fatal:{}", category));
+    }
+}
+
+fn step_category(kind: &StepKind) -> &'static str {
+    match kind {
+        StepKind::ReleaseNotes => return "release_notes",
+        StepKind::ReleaseMail => return "mail",
+        StepKind::Pipeline(_) => return "pipeline",
+        StepKind::Shell { .. } => return "shell",
+        StepKind::Webhook { .. } => return "webhook",
+        StepKind::Tag {
+            repository: Some(_),
+            ..
+        } => return "tag:git",
+        StepKind::Tag {
+            repository: None, ..
+        } => return "tag:gitlab",
+        StepKind::StaticSite(_) => return "static_site",
+    }
+}
+
+/// Posts the usage to the endpoint. Telemetry never gets in the way, so errors are ignored.
+pub fn send(config: &TelemetryConfig, usage: &Usage) {
+    let report = Report {
+        installation: installation_id(),
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        usage,
+    };
+    let agent = ureq::AgentBuilder::new().timeout(SEND_TIMEOUT).build();
+    let _ = agent.post(&config.endpoint).send_json(&report);
+}
+
+/// `telemetry-id` next to the session file.
+fn id_path() -> Option<PathBuf> {
+    return Some(session::default_path()?.parent()?.join("telemetry-id"));
+}
+
+/// The random id of this installation, created on first use.
+fn installation_id() -> String {
+    let path = id_path();
+    if let Some(id) = path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+    {
+        return id.trim().to_string();
+    }
+    let mut bytes = [0u8; 16];
+    let _ = SystemRandom::new().fill(&mut bytes);
+    let id: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    if let Some(path) = path {
+        if let Some(directory) = path.parent() {
+            let _ = std::fs::create_dir_all(directory);
+        }
+        let _ = std::fs::write(path, &id);
+    }
+    return id;
+}
@@ -0,0 +1,224 @@
+use serde::Deserialize;
+
+use crate::config::ProjectConfig;
+use crate::{is_breaking_flag, App, Changelog, Project};
+
+/// Made up projects the tutorial releases, nothing in them points anywhere real.
+const DEMO_PROJECTS: &str = r#"[
+    {
+        "config": {
+            "name": "shop",
+            "project_id": 1,
+            "title": "Shop",
+            "deploy_command": ["./deploy.sh", "{project_id}", "{version}"]
+        },
+        "changelog": {
+            "next_version_number": 42,
+            "current_time": "2026-01-05 09:00",
+            "commit": {
+                "commit_hash": "3f2a9c1",
+                "title": "Merge branch 'feature/SHOP-101-wishlist'",
+                "author_name": "Anna"
+            },
+            "merge_requests": [
+                {
+                    "ticket_number": "SHOP-101",
+                    "title": "feat(wishlist): share wishlists by link",
+                    "github": "https://gitlab.example.com/demo/shop/-/merge_requests/101",
+                    "flags": "feature"
+                },
+                {
+                    "ticket_number": "SHOP-97",
+                    "title": "fix(cart): keep vouchers after login",
+                    "github": "https://gitlab.example.com/demo/shop/-/merge_requests/97",
+                    "flags": "bugfix"
+                },
+                {
+                    "ticket_number": "SHOP-88",
+                    "title": "feat(orders): split the address table",
+                    "github": "https://gitlab.example.com/demo/shop/-/merge_requests/88",
+                    "flags": "feature, migration"
+                },
+                {
+                    "ticket_number": "SHOP-93",
+                    "title": "chore: update dependencies",
+                    "github": "https://gitlab.example.com/demo/shop/-/merge_requests/93",
+                    "flags": ""
+                }
+            ]
+        }
+    },
+    {
+        "config": {
+            "name": "admin",
+            "project_id": 2,
+            "title": "Admin",
+            "deploy_command": ["./deploy.sh", "{project_id}", "{version}"],
+            "confirm": true
+        },
+        "changelog": {
+            "next_version_number": 17,
+            "current_time": "2026-01-05 09:00",
+            "commit": {
+                "commit_hash": "b41e07d",
+                "title": "Merge branch 'fix/SHOP-97-vouchers'",
+                "author_name": "Ben"
+            },
+            "merge_requests": [
+                {
+                    "ticket_number": "SHOP-97",
+                    "title": "fix(vouchers): show vouchers used after login",
+                    "github": "https://gitlab.example.com/demo/admin/-/merge_requests/12",
+                    "flags": "bugfix"
+                },
+                {
+                    "ticket_number": "SHOP-102",
+                    "title": "feat(export): export orders as CSV",
+                    "github": "https://gitlab.example.com/demo/admin/-/merge_requests/14",
+                    "flags": "feature"
+                }
+            ]
+        }
+    }
+]"#;
+
+#[derive(Deserialize)]
+struct DemoProject {
+    config: ProjectConfig,
+    changelog: Changelog,
+}
+
+/// The projects of the tutorial with their changelogs already loaded.
+pub fn demo_projects() -> Vec<Project> {
+    let projects: Vec<DemoProject> =
+        serde_json::from_str(DEMO_PROJECTS).expect("the demo projects are valid");
+    return projects
+        .into_iter()
+        .map(|project| Project::new(project.config, project.changelog))
+        .collect();
+}
+
+/// One thing the tutorial explains and asks to do.
+pub struct TutorialStep {
+    pub title: &'static str,
+    /// Keys are written in backticks and get highlighted
+    pub text: &'static str,
+    /// Whether the action the step asks for was done
+    done: fn(&App) -> bool,
+}
+
+impl TutorialStep {
+    pub fn is_done(&self, app: &App) -> bool {
+        return (self.done)(app);
+    }
+}
+
+const STEPS: &[TutorialStep] = &[
+    TutorialStep {
+        title: "The overview",
+        text: "Every project has a tab with what was merged since its last release and the \
+               version it gets next. Switch to the next project with `Right`.",
+        done: |app| app.selected == 1,
+    },
+    TutorialStep {
+        title: "Merge requests",
+        text: "Go back with `Left`. `Down`/`j` and `Up`/`k` select merge requests, `o` opens \
+               one in the browser. Red badges mark breaking changes and migrations, select the \
+               merge request with the migration.",
+        done: |app| {
+            let project = &app.projects[app.selected];
+            let Some(changelog) = &project.changelog else {
+                return false;
+            };
+            return app
+                .merge_requests
+                .selected()
+                .and_then(|row| changelog.merge_requests.get(row))
+                .is_some_and(|merge_request| {
+                    merge_request
+                        .flags
+                        .iter()
+                        .any(|flag| is_breaking_flag(flag))
+                });
+        },
+    },
+    TutorialStep {
+        title: "Excluding",
+        text: "Say the migration is not ready yet. `x` pulls the selected merge request from \
+               the release: it stays in the list, but is left out of the release notes, mail \
+               and notifications. Exclude it.",
+        done: |app| {
+            app.projects
+                .iter()
+                .any(|project| !project.excluded.is_empty())
+        },
+    },
+    TutorialStep {
+        title: "Undo",
+        text: "It turned out fine after all. `Ctrl+Z` undoes excluding (and toggling options), \
+               `Ctrl+Y` redoes it. Put the merge request back.",
+        done: |app| {
+            app.projects
+                .iter()
+                .all(|project| project.excluded.is_empty())
+        },
+    },
+    TutorialStep {
+        title: "Comparing",
+        text: "The same ticket can touch several projects. `v` shows two projects side by \
+               side and highlights tickets that are in both. Compare them.",
+        done: |app| app.compare.is_some(),
+    },
+    TutorialStep {
+        title: "Deployment view",
+        text: "SHOP-97 is part of both releases. Leave the comparison with `Esc`, then open the \
+               deployment view with `c`.",
+        done: |app| app.ready_for_deployment,
+    },
+    TutorialStep {
+        title: "Options",
+        text: "`Up`/`Down` select an option and `Space` checks it. Options that reach a lot of \
+               people or production ask first. Check \"Send Release Mail\" and confirm with `y`.",
+        done: |app| app.deployment.selected_options[0].value,
+    },
+    TutorialStep {
+        title: "Deploying",
+        text: "The tutorial only does dry runs (`d` outside of it): every step tells what it \
+               would do. Start the deployment with `Enter` and watch the steps run.",
+        done: |app| app.deployment.has_finished(),
+    },
+    TutorialStep {
+        title: "Command palette",
+        text: "A real deployment can be cancelled with `Esc`, and `h` lists past ones. Everything \
+               can also be found in the command palette, open it with `Ctrl+P`.",
+        done: |app| app.palette.is_some(),
+    },
+    TutorialStep {
+        title: "Done",
+        text: "That was a full release. Close the palette with `Esc` and quit with `q`, next \
+               time start without `--tutorial` to release for real.",
+        done: |_| false,
+    },
+];
+
+/// Walks through a release of the demo projects, one step at a time.
+#[derive(Debug, Default)]
+pub struct Tutorial {
+    /// Index into the steps
+    step: usize,
+}
+
+impl Tutorial {
+    pub fn step(&self) -> &'static TutorialStep {
+        return &STEPS[self.step];
+    }
+
+    /// Position of the current step, counted from 1, and the number of steps.
+    pub fn progress(&self) -> (usize, usize) {
+        return (self.step + 1, STEPS.len());
+    }
+
+    pub fn next(&mut self) {
+        self.step = (self.step + 1).min(STEPS.len() - 1);
+    }
+}
@@ -0,0 +1,49 @@
+/// A reversible change made in the UI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiChange {
+    /// Index into `Deployment.selected_options`, toggling again reverts it
+    ToggleOption(usize),
+    /// Switches between a real deployment and a dry run
+    ToggleDryRun,
+    /// Row of a project's merge request table pulled from the release, or put back
+    ExcludeMergeRequest { project: usize, row: usize },
+}
+
+/// Changes that Ctrl+Z reverts and Ctrl+Y applies again.
+#[derive(Default)]
+pub struct UndoStack {
+    undo: Vec<UiChange>,
+    redo: Vec<UiChange>,
+}
+
+impl UndoStack {
+    /// Records a change that was just made, which drops everything that could be redone.
+    pub fn push(&mut self, change: UiChange) {
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    /// The change `undo` would revert, without reverting it.
+    pub fn next_undo(&self) -> Option<&UiChange> {
+        return self.undo.last();
+    }
+
+    /// The change `redo` would apply again, without applying it.
+    pub fn next_redo(&self) -> Option<&UiChange> {
+        return self.redo.last();
+    }
+
+    /// The change to revert, it moves to the redo stack.
+    pub fn undo(&mut self) -> Option<UiChange> {
+        let change = self.undo.pop()?;
+        self.redo.push(change.clone());
+        return Some(change);
+    }
+
+    /// The change to apply again, it moves back to the undo stack.
+    pub fn redo(&mut self) -> Option<UiChange> {
+        let change = self.redo.pop()?;
+        self.undo.push(change.clone());
+        return Some(change);
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::Result;
+
+use crate::config::Config;
+use crate::fetcher::ChangelogFetcher;
+use crate::notifications::Notifier;
+use crate::{Changelog, MergeRequest};
+
+/// What is known about a project from the previous refresh.
+#[derive(Default)]
+struct WatchedProject {
+    pending: Option<usize>,
+    /// Links of the merge requests already seen, so only new ones alert
+    seen: HashSet<String>,
+}
+
+/// Refreshes the changelogs every `watch.interval` seconds and prints the pending merge
+/// requests per project. Alerts on the webhooks and the desktop when a project's pending
+/// count reaches `watch.threshold` or a merge request with a watched flag is merged.
+pub fn watch(config: Arc<Config>, token: &str) -> Result<()> {
+    let fetcher = ChangelogFetcher::new(Arc::clone(&config), token);
+    let notifier = Notifier::new(&config.notifications);
+    let mut projects: Vec<WatchedProject> = config
+        .projects
+        .iter()
+        .map(|_| WatchedProject::default())
+        .collect();
+    loop {
+        fetcher.fetch_all();
+        let time = chrono::Local::now().format("%H:%M");
+        for _ in 0..projects.len() {
+            let update = fetcher.wait();
+            let title = &config.projects[update.project].title;
+            let changelog = match update.changelog {
+                Ok(changelog) => changelog,
+                Err(error) => {
+                    println!("{} {}: {}", time, title, error);
+                    continue;
+                }
+            };
+            println!(
+                "{} {}: {} pending merge requests",
+                time,
+                title,
+                changelog.merge_requests.len()
+            );
+            for alert in alerts(&config, title, &changelog, &mut projects[update.project]) {
+                println!("{} {}", time, alert);
+                if let Err(error) = notifier.notify(&alert) {
+                    println!("{} Notification failed: {}", time, error);
+                }
+                if config.watch.desktop {
+                    notify_desktop(&alert);
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_secs(config.watch.interval));
+    }
+}
+
+/// Alerts for this refresh, updating what is known about the project.
+fn alerts(
+    config: &Config,
+    title: &str,
+    changelog: &Changelog,
+    project: &mut WatchedProject,
+) -> Vec<String> {
+    let mut alerts = vec![];
+    let pending = changelog.merge_requests.len();
+    if let Some(threshold) = config.watch.threshold {
+        let before = project.pending.unwrap_or(0);
+        if before < threshold && pending >= threshold {
+            alerts.push(format!(
+                "{} has {} pending merge requests, time to deploy",
+                title, pending
+            ));
+        }
+    }
+    // Everything pending at startup counts as seen, only merges after that alert
+    let first_refresh = project.pending.is_none();
+    for merge_request in changelog.merge_requests.iter() {
+        if !project.seen.insert(merge_request.github.clone()) || first_refresh {
+            continue;
+        }
+        if let Some(flag) = watched_flag(config, merge_request) {
+            alerts.push(format!(
+                "{} merged into {}: {} {} ({})",
+                flag, title, merge_request.ticket_number, merge_request.title, merge_request.github
+            ));
+        }
+    }
+    project.pending = Some(pending);
+    return alerts;
+}
+
+fn watched_flag(config: &Config, merge_request: &MergeRequest) -> Option<String> {
+    return merge_request
+        .flags
+        .iter()
+        .find(|flag| {
+            config
+                .watch
+                .flags
+                .iter()
+                .any(|watched| watched.eq_ignore_ascii_case(flag))
+        })
+        .cloned();
+}
+
+/// Shows a desktop notification through notify-send, where it is not installed nothing happens.
+fn notify_desktop(message: &str) {
+    let _ = std::process::Command::new("notify-send")
+        .args(["deployment-tool", message])
+        .status();
+}